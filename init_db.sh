@@ -0,0 +1,19 @@
+#!/bin/sh
+# Creates db_v2.sqlite3, the database DATABASE_URL in .env points the sqlx
+# query! macros at, from the migrations the server runs on startup. The
+# result is committed so a fresh clone builds without sqlite3, run this
+# after adding a migration and commit the database with it.
+set -e
+cd "$(dirname "$0")"
+DB=db_v2.sqlite3
+rm -f $DB $DB-shm $DB-wal
+sqlite3 $DB "create table schema_version (
+    version integer primary key not null,
+    applied_at datetime not null default(current_timestamp)
+)"
+for f in mini_rustdesk_server/migrations/*.sql; do
+    version=$(basename "$f" | cut -d_ -f1 | sed 's/^0*//')
+    sqlite3 $DB < "$f"
+    sqlite3 $DB "insert into schema_version(version) values($version)"
+done
+echo "Created $DB at schema version $version"
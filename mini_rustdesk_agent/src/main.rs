@@ -1,3 +1,142 @@
-fn main() {
-    println!("Hello, world!");
+use anyhow::bail;
+use flexi_logger::*;
+use mini_rustdesk_server::common::{get_arg, get_arg_or, get_server_sk, init_args};
+use mini_rustdesk_server::message::{
+    login_request, message, Hash, LoginRequest, LoginResponse, Message, PeerInfo,
+};
+use mini_rustdesk_server::tcp::{listen_any, FramedStream};
+use mini_rustdesk_server::{codec, file_transfer, handshake, ResultType};
+use protobuf::Message as _;
+use sodiumoxide::{
+    crypto::{hash::sha256, sign},
+    randombytes::randombytes,
+    utils::memcmp,
+};
+use std::{net::SocketAddr, sync::Arc};
+
+const DEFAULT_PORT: u16 = 21118;
+const LOGIN_TIMEOUT: u64 = 30_000;
+
+/// Accepts file transfer sessions from RustDesk style clients: the handshake
+/// proves the agent's `id` to the client, the password login proves the
+/// client to the agent.
+struct Agent {
+    id: String,
+    sk: sign::SecretKey,
+    password: String,
+}
+
+fn main() -> ResultType<()> {
+    let _logger = Logger::try_with_env_or_str("info")?
+        .log_to_stdout()
+        .format(opt_format)
+        .write_mode(WriteMode::Async)
+        .start()?;
+    let args = format!(
+        "-p, --port=[NUMBER(default={DEFAULT_PORT})] 'Sets the listening port'
+        -i, --id=[ID] 'Sets the id the agent signs its handshake with'
+        -k, --key=[KEY] 'Sets the signing key, - for the id_ed25519 file'
+        --password=[PASSWORD] 'Sets the password clients log in with'",
+    );
+    init_args(&args, "agent", "RustDesk File Transfer Agent");
+    let port = get_arg_or("port", DEFAULT_PORT.to_string()).parse::<u16>()?;
+    let id = get_arg("id");
+    if id.is_empty() {
+        bail!("The id is required");
+    }
+    let password = get_arg("password");
+    if password.is_empty() {
+        bail!("A password is required");
+    }
+    let sk = match get_server_sk(&get_arg_or("key", "-".to_owned())).1 {
+        Some(sk) => sk,
+        None => bail!("A signing key is required"),
+    };
+    run(port, Arc::new(Agent { id, sk, password }))
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn run(port: u16, agent: Arc<Agent>) -> ResultType<()> {
+    let listener = listen_any(port, true).await?;
+    log::info!("Listening on tcp :{}", port);
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        stream.set_nodelay(true).ok();
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            let mut stream = FramedStream::from(stream, addr);
+            if let Err(err) = agent.handle(&mut stream, addr).await {
+                log::info!("Connection from {} closed: {}", addr, err);
+            }
+        });
+    }
+}
+
+fn random_string() -> String {
+    randombytes(8).iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// What a RustDesk client sends as `LoginRequest.password`.
+fn hash_password(password: &str, salt: &str, challenge: &str) -> Vec<u8> {
+    let salted = sha256::hash(&[password.as_bytes(), salt.as_bytes()].concat());
+    sha256::hash(&[&salted[..], challenge.as_bytes()].concat()).0.to_vec()
+}
+
+async fn send_login_error(stream: &mut FramedStream, error: &str) -> ResultType<()> {
+    let mut res = LoginResponse::new();
+    res.set_error(error.to_owned());
+    let mut msg = Message::new();
+    msg.set_login_response(res);
+    stream.send(&msg).await
+}
+
+impl Agent {
+    async fn handle(&self, stream: &mut FramedStream, addr: SocketAddr) -> ResultType<()> {
+        handshake::respond(stream, &self.id, &self.sk).await?;
+        let (salt, challenge) = (random_string(), random_string());
+        let mut msg = Message::new();
+        msg.set_hash(Hash {
+            salt: salt.clone(),
+            challenge: challenge.clone(),
+            ..Default::default()
+        });
+        stream.send(&msg).await?;
+        let req = self.next_login(stream).await?;
+        if !matches!(req.union, Some(login_request::Union::FileTransfer(_))) {
+            send_login_error(stream, "Only file transfer is supported").await?;
+            bail!("Not a file transfer login");
+        }
+        if !memcmp(&req.password, &hash_password(&self.password, &salt, &challenge)) {
+            send_login_error(stream, "Wrong Password").await?;
+            bail!("Wrong password");
+        }
+        let mut pi = PeerInfo {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            ..Default::default()
+        };
+        let compress_codec = codec::accept(&req, &mut pi);
+        let mut msg = Message::new();
+        let mut res = LoginResponse::new();
+        res.set_peer_info(pi);
+        msg.set_login_response(res);
+        stream.send(&msg).await?;
+        log::info!("File transfer from {} ({}) logged in", req.my_id, addr);
+        file_transfer::serve(stream, compress_codec).await
+    }
+
+    async fn next_login(&self, stream: &mut FramedStream) -> ResultType<LoginRequest> {
+        loop {
+            match stream.next_timeout(LOGIN_TIMEOUT).await {
+                Some(Ok(bytes)) => {
+                    if let Some(message::Union::LoginRequest(req)) =
+                        Message::parse_from_bytes(&bytes)?.union
+                    {
+                        return Ok(req);
+                    }
+                }
+                Some(Err(err)) => return Err(err.into()),
+                None => bail!("Login timeout"),
+            }
+        }
+    }
 }
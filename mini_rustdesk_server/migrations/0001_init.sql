@@ -0,0 +1,30 @@
+create table if not exists peer (
+    guid blob primary key not null,
+    id varchar(100) not null,
+    uuid blob not null,
+    pk blob not null,
+    created_at datetime not null default(current_timestamp),
+    user blob,
+    status tinyint,
+    note varchar(300),
+    info text not null
+) without rowid;
+create unique index if not exists index_peer_id on peer (id);
+create index if not exists index_peer_user on peer (user);
+create index if not exists index_peer_created_at on peer (created_at);
+create index if not exists index_peer_status on peer (status);
+create table if not exists relay_session (
+    uuid varchar(100) not null,
+    peer_id varchar(100) not null,
+    ip varchar(100) not null,
+    peer_ip varchar(100) not null,
+    conn_type tinyint not null,
+    started_at datetime not null,
+    ended_at datetime not null,
+    bytes_up integer not null,
+    bytes_down integer not null,
+    close_reason text not null
+);
+create index if not exists index_relay_session_uuid on relay_session (uuid);
+create index if not exists index_relay_session_peer_id on relay_session (peer_id);
+create index if not exists index_relay_session_ended_at on relay_session (ended_at);
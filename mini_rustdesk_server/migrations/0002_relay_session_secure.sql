@@ -0,0 +1 @@
+alter table relay_session add column secure boolean not null default 0;
@@ -0,0 +1,15 @@
+create table if not exists peer (
+    guid bytea primary key not null,
+    id varchar(100) not null,
+    uuid bytea not null,
+    pk bytea not null,
+    created_at timestamp not null default(current_timestamp),
+    "user" bytea,
+    status smallint,
+    note varchar(300),
+    info text not null
+);
+create unique index if not exists index_peer_id on peer (id);
+create index if not exists index_peer_user on peer ("user");
+create index if not exists index_peer_created_at on peer (created_at);
+create index if not exists index_peer_status on peer (status);
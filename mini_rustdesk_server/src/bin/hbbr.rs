@@ -0,0 +1,25 @@
+use flexi_logger::*;
+use mini_rustdesk_server::common::{get_arg_or, init_args};
+use mini_rustdesk_server::config::RELAY_PORT;
+use mini_rustdesk_server::{relay_server, ResultType};
+
+fn main() -> ResultType<()> {
+    let _logger = Logger::try_with_env_or_str("info")?
+        .log_to_stdout()
+        .format(opt_format)
+        .write_mode(WriteMode::Async)
+        .start()?;
+    let args = format!(
+        "-p, --port=[NUMBER(default={RELAY_PORT})] 'Sets the listening port'
+        -k, --key=[KEY] 'Only allow the client with the same key'
+        -R, --rendezvous-servers=[HOSTS] 'Sends load heartbeats to these rendezvous servers, separated by comma'
+        --relay-peers=[HOSTS] 'Forwards requests for other relays to these peers, separated by comma'
+        --db-url=[URL] 'Sets the relay audit database, a sqlite file path'
+        --ipc-path=[PATH] 'Serves local control commands on this unix socket'",
+    );
+    init_args(&args, "hbbr", "RustDesk Relay Server");
+    let port = get_arg_or("port", RELAY_PORT.to_string());
+    let key = get_arg_or("key", "-".to_owned());
+    relay_server::start(&port, &key)?;
+    Ok(())
+}
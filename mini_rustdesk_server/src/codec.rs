@@ -0,0 +1,150 @@
+use crate::compress::{self, MAX};
+use crate::config::COMPRESS_LEVEL;
+use crate::message::{Clipboard, CompressCodec, LoginRequest, PeerInfo};
+use crate::ResultType;
+use anyhow::bail;
+use std::time::Duration;
+
+const MIN_ZSTD_LEVEL: i32 = -5;
+const MAX_ZSTD_LEVEL: i32 = 9;
+
+/// The codecs we can handle, preferred first.
+pub const SUPPORTED: &[CompressCodec] = &[
+    CompressCodec::ZSTD,
+    CompressCodec::LZ4,
+    CompressCodec::NO_COMPRESSION,
+];
+
+/// Picks the first codec of `offered` we support. Peers that offer nothing
+/// predate codec negotiation and only know zstd.
+pub fn negotiate(offered: &[CompressCodec]) -> CompressCodec {
+    offered
+        .iter()
+        .find(|x| SUPPORTED.contains(x))
+        .copied()
+        .unwrap_or(CompressCodec::ZSTD)
+}
+
+/// The controlling side offers our codecs with its login.
+pub fn offer(req: &mut LoginRequest) {
+    req.compress_codecs = SUPPORTED.iter().map(|x| (*x).into()).collect();
+}
+
+/// The controlled side picks the codec for the session from `req` and
+/// announces it in the `PeerInfo` of its login response.
+pub fn accept(req: &LoginRequest, pi: &mut PeerInfo) -> CompressCodec {
+    let offered: Vec<CompressCodec> = req
+        .compress_codecs
+        .iter()
+        .filter_map(|x| x.enum_value().ok())
+        .collect();
+    let codec = negotiate(&offered);
+    pi.compress_codec = codec.into();
+    codec
+}
+
+/// The codec the controlled side picked. Peers that predate negotiation
+/// leave it at its default, zstd.
+pub fn accepted(pi: &PeerInfo) -> CompressCodec {
+    pi.compress_codec.enum_value_or_default()
+}
+
+/// Per-transfer encoder state. The zstd level adapts to the link: it goes
+/// down while compressing is slower than sending, and up while the link is
+/// the bottleneck, so fast LANs are not throttled by the cpu.
+pub struct Encoder {
+    codec: CompressCodec,
+    level: i32,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new(CompressCodec::ZSTD)
+    }
+}
+
+impl Encoder {
+    pub fn new(codec: CompressCodec) -> Self {
+        Self {
+            codec,
+            level: COMPRESS_LEVEL,
+        }
+    }
+
+    pub fn codec(&self) -> CompressCodec {
+        self.codec
+    }
+
+    /// Returns the compressed payload, or `None` if it is not worth sending
+    /// compressed, the codec is `NO_COMPRESSION` or compression failed.
+    pub fn encode(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let res = match self.codec {
+            CompressCodec::ZSTD => compress::compress(data, self.level),
+            CompressCodec::LZ4 => Ok(lz4_flex::compress_prepend_size(data)),
+            CompressCodec::NO_COMPRESSION => return None,
+        };
+        match res {
+            Ok(res) if res.len() < data.len() => Some(res),
+            Ok(_) => None,
+            Err(err) => {
+                log::info!("Failed to compress: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Feeds back how long the last payload took to compress and to send.
+    pub fn update(&mut self, compress_elapsed: Duration, send_elapsed: Duration) {
+        if self.codec != CompressCodec::ZSTD {
+            return;
+        }
+        if compress_elapsed > send_elapsed {
+            self.level = (self.level - 1).max(MIN_ZSTD_LEVEL);
+        } else if compress_elapsed * 4 < send_elapsed {
+            self.level = (self.level + 1).min(MAX_ZSTD_LEVEL);
+        }
+    }
+}
+
+pub fn decode(codec: CompressCodec, data: &[u8]) -> ResultType<Vec<u8>> {
+    match codec {
+        CompressCodec::ZSTD => compress::decompress(data),
+        CompressCodec::LZ4 => {
+            if data.len() < 4 {
+                bail!("Truncated lz4 payload");
+            }
+            let size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            if size > MAX {
+                bail!("Decompressed data exceeds {} bytes", MAX);
+            }
+            Ok(lz4_flex::decompress_size_prepended(data)?)
+        }
+        CompressCodec::NO_COMPRESSION => Ok(data.to_vec()),
+    }
+}
+
+/// Builds a `Clipboard` message for `content`, compressed with `codec` if
+/// that makes it smaller.
+pub fn new_clipboard(content: &[u8], codec: CompressCodec) -> Clipboard {
+    let encoder = Encoder::new(codec);
+    match encoder.encode(content) {
+        Some(compressed) => Clipboard {
+            compress: true,
+            content: compressed.into(),
+            codec: codec.into(),
+            ..Default::default()
+        },
+        None => Clipboard {
+            content: content.to_vec().into(),
+            ..Default::default()
+        },
+    }
+}
+
+pub fn get_clipboard_content(clipboard: &Clipboard) -> ResultType<Vec<u8>> {
+    if clipboard.compress {
+        decode(clipboard.codec.enum_value_or_default(), &clipboard.content)
+    } else {
+        Ok(clipboard.content.to_vec())
+    }
+}
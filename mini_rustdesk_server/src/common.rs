@@ -1,13 +1,14 @@
 use clap::App;
 use std::time::Instant;
 use sodiumoxide::crypto::sign;
-use anyhow::{Context, Result};
+use anyhow::Result;
 use std::{
     io::prelude::*,
     io::Read,
     net::SocketAddr,
     net::IpAddr,
-    time::SystemTime,
+    net::{Ipv4Addr, SocketAddrV4},
+    time::{SystemTime, UNIX_EPOCH},
 };
 use tokio::time;
 #[macro_export]
@@ -42,7 +43,9 @@ macro_rules! allow_err {
     };
 }
 
-fn arg_name(name: &str) -> String {
+/// The environment variable of the argument `name`, e.g. `POLICY-PORT-FORWARD`
+/// for `policy-port_forward`.
+pub(crate) fn arg_name(name: &str) -> String {
     name.to_uppercase().replace('_', "-")
 }
 
@@ -145,4 +148,87 @@ pub fn try_into_v4(addr: SocketAddr) -> SocketAddr {
         }
         _ => addr,
     }
-}
\ No newline at end of file
+}
+
+pub fn get_server_sk(key: &str) -> (String, Option<sign::SecretKey>) {
+    let mut key = key.to_owned();
+    let mut out_sk = None;
+    if let Ok(sk) = base64::decode(&key) {
+        if sk.len() == sign::SECRETKEYBYTES {
+            log::info!("The key is a crypto private key");
+            key = base64::encode(&sk[(sign::SECRETKEYBYTES / 2)..]);
+            out_sk = sign::SecretKey::from_slice(&sk);
+        }
+    }
+
+    if key == "-" || key == "_" {
+        let (pk, sk) = gen_sk(300);
+        key = pk;
+        out_sk = sk;
+    }
+
+    if !key.is_empty() {
+        log::info!("Key: {}", key);
+    }
+
+    (key, out_sk)
+}
+
+pub fn normalize_relay_addr(addr: &str) -> String {
+    let addr = addr.to_lowercase();
+    if addr.contains(':') {
+        addr
+    } else {
+        format!("{addr}:{}", crate::config::RELAY_PORT)
+    }
+}
+
+/// The obfuscated socket address format of `PunchHole.socket_addr` and
+/// friends, compatible with RustDesk clients.
+pub struct AddrMangle();
+
+impl AddrMangle {
+    pub fn encode(addr: SocketAddr) -> Vec<u8> {
+        match addr {
+            SocketAddr::V4(addr_v4) => {
+                let tm = (SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|x| x.as_micros())
+                    .unwrap_or(0) as u32) as u128;
+                let ip = u32::from_le_bytes(addr_v4.ip().octets()) as u128;
+                let port = addr.port() as u128;
+                let v = ((ip + tm) << 49) | (tm << 17) | (port + (tm & 0xFFFF));
+                let bytes = v.to_le_bytes();
+                let n_padding = bytes.iter().rev().take_while(|x| **x == 0).count();
+                bytes[..(16 - n_padding)].to_vec()
+            }
+            SocketAddr::V6(addr_v6) => {
+                let mut x = addr_v6.ip().octets().to_vec();
+                x.extend(addr_v6.port().to_le_bytes());
+                x
+            }
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> SocketAddr {
+        if bytes.len() > 16 {
+            if bytes.len() != 18 {
+                return crate::config::get_any_listen_addr(false);
+            }
+            let mut tmp = [0u8; 16];
+            tmp.copy_from_slice(&bytes[..16]);
+            let port = u16::from_le_bytes([bytes[16], bytes[17]]);
+            return SocketAddr::new(IpAddr::V6(std::net::Ipv6Addr::from(tmp)), port);
+        }
+        let mut padded = [0u8; 16];
+        padded[..bytes.len()].copy_from_slice(bytes);
+        let number = u128::from_le_bytes(padded);
+        let tm = (number >> 17) & (u32::MAX as u128);
+        let ip = (((number >> 49).wrapping_sub(tm)) as u32).to_le_bytes();
+        let port = (number & 0xFFFFFF).wrapping_sub(tm & 0xFFFF);
+        SocketAddr::V4(SocketAddrV4::new(
+            Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]),
+            port as u16,
+        ))
+    }
+}
@@ -1,42 +1,79 @@
+use crate::ResultType;
+use anyhow::bail;
 use std::cell::RefCell;
-use zstd::block::{Compressor, Decompressor};
+use zstd::{
+    block::Compressor,
+    stream::raw::{Decoder, InBuffer, Operation, OutBuffer},
+};
+
+// upper bound of one decompressed message, against decompression bombs
+pub const MAX: usize = 1024 * 1024 * 64;
+const CHUNK: usize = 64 * 1024;
 
 thread_local! {
     static COMPRESSOR: RefCell<Compressor> = RefCell::new(Compressor::new());
-    static DECOMPRESSOR: RefCell<Decompressor> = RefCell::new(Decompressor::new());
 }
 
+pub fn compress(data: &[u8], level: i32) -> ResultType<Vec<u8>> {
+    COMPRESSOR.with(|c| {
+        let mut c = c.try_borrow_mut()?;
+        Ok(c.compress(data, level)?)
+    })
+}
+
+/// Decompresses one zstd frame. The output size is not guessed from the
+/// input, it grows as needed up to 64 MiB.
+pub fn decompress(data: &[u8]) -> ResultType<Vec<u8>> {
+    let mut decoder = Decoder::new()?;
+    let (out, remaining) = run_decoder(&mut decoder, data)?;
+    if remaining != 0 {
+        bail!("Incomplete zstd frame");
+    }
+    Ok(out)
+}
 
-pub fn compress(data: &[u8], level: i32) -> Vec<u8> {
+fn run_decoder(decoder: &mut Decoder<'static>, data: &[u8]) -> ResultType<(Vec<u8>, usize)> {
+    let mut input = InBuffer::around(data);
     let mut out = Vec::new();
-    COMPRESSOR.with(|c| {
-        if let Ok(mut c) = c.try_borrow_mut() {
-            match c.compress(data, level) {
-                Ok(res) => out = res,
-                Err(err) => {
-                    log::info!("Failed to compress: {}", err);
-                }
-            }
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let (n, remaining) = {
+            let mut output = OutBuffer::around(&mut buf[..]);
+            let remaining = decoder.run(&mut input, &mut output)?;
+            (output.pos(), remaining)
+        };
+        out.extend_from_slice(&buf[..n]);
+        if out.len() > MAX {
+            bail!("Decompressed data exceeds {} bytes", MAX);
         }
-    });
-    out
+        // the frame is complete, calling again would start the next one
+        if remaining == 0 {
+            return Ok((out, 0));
+        }
+        // a full buffer may mean more output is pending
+        if input.pos() == data.len() && n < buf.len() {
+            return Ok((out, remaining));
+        }
+    }
 }
 
-pub fn decompress(data: &[u8]) -> Vec<u8> {
-    let mut out = Vec::new();
-    DECOMPRESSOR.with(|d| {
-        if let Ok(mut d) = d.try_borrow_mut() {
-            const MAX: usize = 1024 * 1024 * 64;
-            const MIN: usize = 1024 * 1024;
-            let mut n = 30 * data.len();
-            n = n.clamp(MIN, MAX);
-            match d.decompress(data, n) {
-                Ok(res) => out = res,
-                Err(err) => {
-                    log::info!("Failed to decompress: {}", err);
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // around and at multiples of the output chunk
+        for len in [0, 10, CHUNK - 1, CHUNK, CHUNK + 1, 2 * CHUNK, 1_000_000] {
+            let data: Vec<u8> = (0..len).map(|x| (x % 7) as u8).collect();
+            assert_eq!(decompress(&compress(&data, 3).unwrap()).unwrap(), data);
         }
-    });
-    out
+    }
+
+    #[test]
+    fn truncated() {
+        let data = vec![1u8; 3 * CHUNK];
+        let compressed = compress(&data, 3).unwrap();
+        assert!(decompress(&compressed[..compressed.len() - 1]).is_err());
+    }
 }
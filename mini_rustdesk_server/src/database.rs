@@ -1,14 +1,22 @@
 use async_trait::async_trait;
 use crate::ResultType;
+use anyhow::bail;
+use crate::common::{get_arg, get_arg_or};
 use sqlx::{
-    sqlite::SqliteConnectOptions, ConnectOptions, Connection, Error as SqlxError, SqliteConnection,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
+    ConnectOptions, Connection, Error as SqlxError, Executor, SqliteConnection,
 };
-use std::{ops::DerefMut, str::FromStr};
+use crate::pg_database::PgDatabase;
+use std::{collections::HashMap, ops::DerefMut, str::FromStr, sync::Arc, time::Duration};
+use tokio::sync::{Mutex, RwLock};
 
 type Pool = deadpool::managed::Pool<DbPool>;
 
+const DEFAULT_POOL_SIZE: usize = 4;
+const DEFAULT_BUSY_TIMEOUT: u64 = 5_000; // in ms
+
 pub struct DbPool{
-    url:String,
+    options: SqliteConnectOptions,
 }
 
 
@@ -17,9 +25,7 @@ impl deadpool::managed::Manager for DbPool {
     type Type = SqliteConnection;
     type Error = SqlxError;
     async fn create(&self) -> Result<SqliteConnection, SqlxError> {
-        let mut opt = SqliteConnectOptions::from_str(&self.url).unwrap();
-        opt.log_statements(log::LevelFilter::Debug);
-        SqliteConnection::connect_with(&opt).await
+        SqliteConnection::connect_with(&self.options).await
     }
     async fn recycle(
         &self,
@@ -29,12 +35,19 @@ impl deadpool::managed::Manager for DbPool {
     }
 }
 
+/// A sqlite database with one writer connection, so that concurrent writes
+/// queue here instead of failing with "database is locked", and a pool of
+/// readers that, in WAL mode, are not blocked by the writer. `db-pool-size`
+/// (default 4) only sizes the read pool, there is always exactly one writer.
+/// Open it with `Database::shared` so that the whole process keeps to that
+/// one writer per file.
 #[derive(Clone)]
 pub struct Database {
     pool: Pool,
+    read_pool: Pool,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Peer {
     pub guid: Vec<u8>,
     pub id: String,
@@ -45,7 +58,120 @@ pub struct Peer {
     pub status: Option<i64>,
 }
 
+/// Storage of registered peers. `Database` keeps them in sqlite,
+/// `PgDatabase` in postgres for several rendezvous servers sharing one
+/// registry, and `MemoryDatabase` nowhere, for tests and throwaway setups.
+#[async_trait]
+pub trait PeerStore: Send + Sync {
+    async fn get_peer_by_id(&self, id: &str) -> ResultType<Option<Peer>>;
+    async fn get_peer_by_guid(&self, guid: &[u8]) -> ResultType<Option<Peer>>;
+    /// Returns the guid of the new peer.
+    async fn insert_peer(&self, id: &str, uuid: &[u8], pk: &[u8], info: &str)
+        -> ResultType<Vec<u8>>;
+    async fn update_pk_by_guid(&self, guid: &[u8], id: &str, pk: &[u8], info: &str)
+        -> ResultType<()>;
+}
+
+/// Opens the peer store for `url`: `postgres://...` or `postgresql://...`,
+/// `memory`, or else a sqlite file path, optionally prefixed with
+/// `sqlite://`.
+pub async fn open_peer_store(url: &str) -> ResultType<Arc<dyn PeerStore>> {
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Ok(Arc::new(PgDatabase::new(url).await?))
+    } else if url == "memory" || url == "memory://" {
+        Ok(Arc::new(MemoryDatabase::default()))
+    } else {
+        let path = url.strip_prefix("sqlite://").unwrap_or(url);
+        Ok(Arc::new(Database::shared(path).await?))
+    }
+}
+
+/// One finished relay session, as recorded in the `relay_session` table.
+#[derive(Default)]
+pub struct RelaySession {
+    pub uuid: String,
+    pub peer_id: String,
+    pub ip: String,
+    pub peer_ip: String,
+    pub conn_type: i32,
+    pub secure: bool,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub bytes_up: i64,
+    pub bytes_down: i64,
+    pub close_reason: String,
+}
+
+/// Up-migrations in order, entry `n` takes the schema from version `n - 1`
+/// to `n`. Never edit a released entry, append a new one. `init_db.sh` in
+/// the repository root applies the same files to the database the `query!`
+/// macros are checked against.
+const MIGRATIONS: &[(i64, &str)] = &[
+    (1, include_str!("../migrations/0001_init.sql")),
+    (2, include_str!("../migrations/0002_relay_session_secure.sql")),
+];
+const SCHEMA_VERSION: i64 = MIGRATIONS.len() as _;
+
+/// Connection settings from `db-journal-mode` (default WAL), `db-synchronous`
+/// (default NORMAL, which is safe with WAL) and `db-busy-timeout` in ms.
+fn connect_options(url: &str) -> ResultType<SqliteConnectOptions> {
+    let journal_mode = get_arg_or("db-journal-mode", "wal".to_owned());
+    let synchronous = get_arg_or("db-synchronous", "normal".to_owned());
+    let busy_timeout = get_arg("db-busy-timeout")
+        .parse::<u64>()
+        .unwrap_or(DEFAULT_BUSY_TIMEOUT);
+    log::info!(
+        "DB journal mode: {}, synchronous: {}, busy timeout: {}ms",
+        journal_mode,
+        synchronous,
+        busy_timeout
+    );
+    let mut opt = SqliteConnectOptions::from_str(url)?
+        .journal_mode(SqliteJournalMode::from_str(&journal_mode)?)
+        .synchronous(SqliteSynchronous::from_str(&synchronous)?)
+        .busy_timeout(Duration::from_millis(busy_timeout));
+    opt.log_statements(log::LevelFilter::Debug);
+    Ok(opt)
+}
+
+/// The sqlite file given by `db-url`, or `db_v2.sqlite3` next to the
+/// executable if that is not set or not a sqlite url.
+pub fn get_db_path() -> String {
+    let url = get_arg("db-url");
+    if !url.is_empty()
+        && !url.starts_with("postgres://")
+        && !url.starts_with("postgresql://")
+        && !url.starts_with("memory")
+    {
+        return url.strip_prefix("sqlite://").unwrap_or(&url).to_owned();
+    }
+    let db_path = match std::env::current_exe() {
+        Ok(exe_path) => exe_path.with_file_name("db_v2.sqlite3"),
+        Err(e) => {
+            log::error!("Failed to get current executable path: {}", e);
+            std::path::PathBuf::from("db_v2.sqlite3")
+        }
+    };
+    db_path.to_str().unwrap_or("db_v2.sqlite3").to_owned()
+}
+
+lazy_static::lazy_static! {
+    static ref OPENED: Mutex<HashMap<String, Database>> = Default::default();
+}
+
 impl Database {
+    /// The database at `path`, opened on first use and shared afterwards,
+    /// e.g. by the peer store and the relay audit log.
+    pub async fn shared(path: &str) -> ResultType<Database> {
+        let mut opened = OPENED.lock().await;
+        if let Some(db) = opened.get(path) {
+            return Ok(db.clone());
+        }
+        let db = Database::new(path).await?;
+        opened.insert(path.to_owned(), db.clone());
+        Ok(db)
+    }
+
     pub async fn new(url: &str) -> ResultType<Database> {
         if !std::path::Path::new(url).exists() {
             match std::fs::File::create(url) {
@@ -53,54 +179,118 @@ impl Database {
                 Err(e) => log::info!("Failed to create database file: {}", e),
             }
         }
+        let options = connect_options(url)?;
         let pool = Pool::new(
             DbPool {
-                url: url.to_owned(),
+                options: options.clone(),
             },
-            2,
+            1,
         );
         let _ = pool.get().await?;
-        let db = Database { pool };
-        db.create_tables().await?;
+        let size = get_arg("db-pool-size")
+            .parse::<usize>()
+            .unwrap_or(DEFAULT_POOL_SIZE)
+            .max(1);
+        log::info!("DB read pool size: {}", size);
+        let read_pool = Pool::new(DbPool { options }, size);
+        let db = Database { pool, read_pool };
+        db.migrate().await?;
         Ok(db)
     }
 
-    async fn create_tables(&self) -> ResultType<()> {
+    /// Brings the schema up to `SCHEMA_VERSION`, each migration in its own
+    /// transaction. Refuses databases written by a newer server, which we
+    /// could silently break.
+    async fn migrate(&self) -> ResultType<()> {
+        let mut conn = self.pool.get().await?;
+        let conn = conn.deref_mut();
+        conn.execute(
+            "create table if not exists schema_version (
+                version integer primary key not null,
+                applied_at datetime not null default(current_timestamp)
+            )",
+        )
+        .await?;
+        let current: i64 = sqlx::query_scalar("select coalesce(max(version), 0) from schema_version")
+            .fetch_one(&mut *conn)
+            .await?;
+        if current > SCHEMA_VERSION {
+            bail!(
+                "Database schema version {} is newer than the supported {}, refusing to start",
+                current,
+                SCHEMA_VERSION
+            );
+        }
+        for (version, sql) in MIGRATIONS.iter().skip(current as _) {
+            log::info!("Migrating database to schema version {}", version);
+            let mut tx = conn.begin().await?;
+            tx.execute(*sql).await?;
+            sqlx::query("insert into schema_version(version) values(?)")
+                .bind(version)
+                .execute(&mut tx)
+                .await?;
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn insert_relay_session(&self, session: &RelaySession) -> ResultType<()> {
         sqlx::query!(
-            "
-            create table if not exists peer (
-                guid blob primary key not null,
-                id varchar(100) not null,
-                uuid blob not null,
-                pk blob not null,
-                created_at datetime not null default(current_timestamp),
-                user blob,
-                status tinyint,
-                note varchar(300),
-                info text not null
-            ) without rowid;
-            create unique index if not exists index_peer_id on peer (id);
-            create index if not exists index_peer_user on peer (user);
-            create index if not exists index_peer_created_at on peer (created_at);
-            create index if not exists index_peer_status on peer (status);
-        "
+            "insert into relay_session(uuid, peer_id, ip, peer_ip, conn_type, started_at, ended_at,
+                bytes_up, bytes_down, close_reason, secure)
+                values(?, ?, ?, ?, ?, datetime(?, 'unixepoch'), datetime(?, 'unixepoch'), ?, ?, ?, ?)",
+            session.uuid,
+            session.peer_id,
+            session.ip,
+            session.peer_ip,
+            session.conn_type,
+            session.started_at,
+            session.ended_at,
+            session.bytes_up,
+            session.bytes_down,
+            session.close_reason,
+            session.secure
         )
         .execute(self.pool.get().await?.deref_mut())
         .await?;
         Ok(())
     }
 
-    pub async fn get_peer_by_id(&self, id: &str) -> ResultType<Option<Peer>> {
+    pub async fn delete_relay_sessions_older_than(&self, days: i64) -> ResultType<u64> {
+        let modifier = format!("-{days} days");
+        Ok(sqlx::query!(
+            "delete from relay_session where ended_at < datetime('now', ?)",
+            modifier
+        )
+        .execute(self.pool.get().await?.deref_mut())
+        .await?
+        .rows_affected())
+    }
+}
+
+#[async_trait]
+impl PeerStore for Database {
+    async fn get_peer_by_id(&self, id: &str) -> ResultType<Option<Peer>> {
         Ok(sqlx::query_as!(
             Peer,
             "select guid, id, uuid, pk, user, status, info from peer where id = ?",
             id
         )
-        .fetch_optional(self.pool.get().await?.deref_mut())
+        .fetch_optional(self.read_pool.get().await?.deref_mut())
+        .await?)
+    }
+
+    async fn get_peer_by_guid(&self, guid: &[u8]) -> ResultType<Option<Peer>> {
+        Ok(sqlx::query_as!(
+            Peer,
+            "select guid, id, uuid, pk, user, status, info from peer where guid = ?",
+            guid
+        )
+        .fetch_optional(self.read_pool.get().await?.deref_mut())
         .await?)
     }
 
-    pub async fn insert_peer(
+    async fn insert_peer(
         &self,
         id: &str,
         uuid: &[u8],
@@ -121,9 +311,9 @@ impl Database {
         Ok(guid)
     }
 
-    pub async fn update_pk_by_guid(
+    async fn update_pk_by_guid(
         &self,
-        guid: &Vec<u8>,
+        guid: &[u8],
         id: &str,
         pk: &[u8],
         info: &str,
@@ -139,14 +329,77 @@ impl Database {
         .await?;
         Ok(())
     }
+}
 
-     pub async fn get_peer_by_guid(&self, guid: &[u8]) -> ResultType<Option<Peer>> {
-        Ok(sqlx::query_as!(
-            Peer,
-            "select guid, id, uuid, pk, user, status, info from peer where guid = ?",
-            guid
-        )
-        .fetch_optional(self.pool.get().await?.deref_mut())
-        .await?)
-    }   
+/// Keeps peers in memory only, they are lost on restart.
+#[derive(Default)]
+pub struct MemoryDatabase {
+    peers: RwLock<HashMap<String, Peer>>,
+}
+
+#[async_trait]
+impl PeerStore for MemoryDatabase {
+    async fn get_peer_by_id(&self, id: &str) -> ResultType<Option<Peer>> {
+        Ok(self.peers.read().await.get(id).cloned())
+    }
+
+    async fn get_peer_by_guid(&self, guid: &[u8]) -> ResultType<Option<Peer>> {
+        Ok(self
+            .peers
+            .read()
+            .await
+            .values()
+            .find(|x| x.guid == guid)
+            .cloned())
+    }
+
+    async fn insert_peer(
+        &self,
+        id: &str,
+        uuid: &[u8],
+        pk: &[u8],
+        info: &str,
+    ) -> ResultType<Vec<u8>> {
+        let mut peers = self.peers.write().await;
+        if peers.contains_key(id) {
+            bail!("Peer {} already exists", id);
+        }
+        let guid = uuid::Uuid::new_v4().as_bytes().to_vec();
+        peers.insert(
+            id.to_owned(),
+            Peer {
+                guid: guid.clone(),
+                id: id.to_owned(),
+                uuid: uuid.to_vec(),
+                pk: pk.to_vec(),
+                info: info.to_owned(),
+                ..Default::default()
+            },
+        );
+        Ok(guid)
+    }
+
+    async fn update_pk_by_guid(
+        &self,
+        guid: &[u8],
+        id: &str,
+        pk: &[u8],
+        info: &str,
+    ) -> ResultType<()> {
+        let mut peers = self.peers.write().await;
+        let old_id = match peers.iter().find(|(_, x)| x.guid == guid) {
+            Some((old_id, _)) => old_id.clone(),
+            None => return Ok(()),
+        };
+        if old_id != id && peers.contains_key(id) {
+            bail!("Peer {} already exists", id);
+        }
+        if let Some(mut peer) = peers.remove(&old_id) {
+            peer.id = id.to_owned();
+            peer.pk = pk.to_vec();
+            peer.info = info.to_owned();
+            peers.insert(id.to_owned(), peer);
+        }
+        Ok(())
+    }
 }
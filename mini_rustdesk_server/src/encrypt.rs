@@ -0,0 +1,314 @@
+use sodiumoxide::crypto::{
+    hash::sha256,
+    secretbox::{self, Key, Nonce},
+};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+const MARKER_DATA: u8 = 0;
+const MARKER_REKEY: u8 = 1;
+const SEQNUM_LEN: usize = std::mem::size_of::<u64>();
+
+/// Why an encrypted stream was closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    /// The frame could not be authenticated with the current key.
+    Decrypt { seqnum: u64 },
+    /// The frame carries a sequence number that was already consumed.
+    Replay { expected: u64, got: u64 },
+    /// Frames were lost or reordered in between.
+    OutOfOrder { expected: u64, got: u64 },
+    /// The frame is too short or has an unknown marker.
+    Malformed,
+    /// A previous error already closed the session.
+    Closed,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decrypt { seqnum } => write!(f, "decryption error at message {}", seqnum),
+            Self::Replay { expected, got } => {
+                write!(f, "replayed message {}, expected {}", got, expected)
+            }
+            Self::OutOfOrder { expected, got } => {
+                write!(f, "out of order message {}, expected {}", got, expected)
+            }
+            Self::Malformed => write!(f, "malformed encrypted message"),
+            Self::Closed => write!(f, "encrypted session closed after an error"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+impl From<CryptoError> for std::io::Error {
+    fn from(err: CryptoError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RekeyPolicy {
+    messages: u64,      // 0 for no limit
+    interval: Duration, // zero for no limit
+}
+
+/// Per-session secretbox state of a `FramedStream`.
+///
+/// By default both directions use the session key with an implicit counter
+/// as nonce, the format every RustDesk peer speaks. Two extensions have to be
+/// agreed on in the handshake. With direction keys each direction has its own
+/// key derived from the session key, so the two counters never meet under the
+/// same key. With rekeying every frame is prefixed with its counter and
+/// carries a marker byte inside the box. A sender flags the last frame under a
+/// key with `MARKER_REKEY` and both sides then move that direction to
+/// `sha256("rekey" || key)`, independently of the other one, so compromising
+/// the current keys does not reveal earlier traffic.
+pub struct Encrypt {
+    send_key: Key,
+    recv_key: Key,
+    send_seqnum: u64,
+    recv_seqnum: u64,
+    rekey: Option<RekeyPolicy>,
+    sent_since_rekey: u64,
+    last_rekey: Instant,
+    failed: bool,
+}
+
+impl Encrypt {
+    /// Uses `key` for both directions.
+    pub fn new(key: Key) -> Self {
+        Self::with_keys(key.clone(), key)
+    }
+
+    /// `initiator` is the side that chose `key`: it sends with
+    /// `sha256("c2s" || key)` and receives with `sha256("s2c" || key)`, the
+    /// peer the other way round.
+    pub fn with_direction_keys(key: Key, initiator: bool) -> Self {
+        let c2s = derive(b"c2s", &key);
+        let s2c = derive(b"s2c", &key);
+        if initiator {
+            Self::with_keys(c2s, s2c)
+        } else {
+            Self::with_keys(s2c, c2s)
+        }
+    }
+
+    fn with_keys(send_key: Key, recv_key: Key) -> Self {
+        Self {
+            send_key,
+            recv_key,
+            send_seqnum: 0,
+            recv_seqnum: 0,
+            rekey: None,
+            sent_since_rekey: 0,
+            last_rekey: Instant::now(),
+            failed: false,
+        }
+    }
+
+    /// Rekeys the sending direction after `messages` frames or `interval`,
+    /// whichever comes first. Changes the frame layout, so the peer must call
+    /// this at the same point of the session.
+    pub fn set_rekey(&mut self, messages: u64, interval: Duration) {
+        self.rekey = Some(RekeyPolicy { messages, interval });
+        self.last_rekey = Instant::now();
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn seal(&mut self, msg: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if self.failed {
+            return Err(CryptoError::Closed);
+        }
+        self.send_seqnum += 1;
+        let nonce = get_nonce(self.send_seqnum);
+        let policy = match self.rekey {
+            Some(policy) => policy,
+            None => return Ok(secretbox::seal(msg, &nonce, &self.send_key)),
+        };
+        self.sent_since_rekey += 1;
+        let due = (policy.messages > 0 && self.sent_since_rekey >= policy.messages)
+            || (!policy.interval.is_zero() && self.last_rekey.elapsed() >= policy.interval);
+        let mut plain = Vec::with_capacity(msg.len() + 1);
+        plain.push(if due { MARKER_REKEY } else { MARKER_DATA });
+        plain.extend_from_slice(msg);
+        let mut out = self.send_seqnum.to_le_bytes().to_vec();
+        out.extend(secretbox::seal(&plain, &nonce, &self.send_key));
+        if due {
+            self.send_key = ratchet(&self.send_key);
+            self.send_seqnum = 0;
+            self.sent_since_rekey = 0;
+            self.last_rekey = Instant::now();
+        }
+        Ok(out)
+    }
+
+    /// Any error is final, the caller is expected to close the stream.
+    pub fn open(&mut self, bytes: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let res = self.open_(bytes);
+        if res.is_err() {
+            self.failed = true;
+        }
+        res
+    }
+
+    fn open_(&mut self, bytes: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if self.failed {
+            return Err(CryptoError::Closed);
+        }
+        let expected = self.recv_seqnum + 1;
+        if self.rekey.is_none() {
+            self.recv_seqnum = expected;
+            return secretbox::open(bytes, &get_nonce(expected), &self.recv_key)
+                .map_err(|_| CryptoError::Decrypt { seqnum: expected });
+        }
+        if bytes.len() < SEQNUM_LEN {
+            return Err(CryptoError::Malformed);
+        }
+        let mut tmp = [0u8; SEQNUM_LEN];
+        tmp.copy_from_slice(&bytes[..SEQNUM_LEN]);
+        let got = u64::from_le_bytes(tmp);
+        if got < expected {
+            return Err(CryptoError::Replay { expected, got });
+        }
+        if got > expected {
+            return Err(CryptoError::OutOfOrder { expected, got });
+        }
+        let mut plain = secretbox::open(&bytes[SEQNUM_LEN..], &get_nonce(got), &self.recv_key)
+            .map_err(|_| CryptoError::Decrypt { seqnum: got })?;
+        if plain.is_empty() {
+            return Err(CryptoError::Malformed);
+        }
+        match plain.remove(0) {
+            MARKER_DATA => self.recv_seqnum = got,
+            MARKER_REKEY => {
+                self.recv_key = ratchet(&self.recv_key);
+                self.recv_seqnum = 0;
+            }
+            _ => return Err(CryptoError::Malformed),
+        }
+        Ok(plain)
+    }
+}
+
+fn get_nonce(seqnum: u64) -> Nonce {
+    let mut nonce = Nonce([0u8; secretbox::NONCEBYTES]);
+    nonce.0[..std::mem::size_of_val(&seqnum)].copy_from_slice(&seqnum.to_le_bytes());
+    nonce
+}
+
+fn derive(label: &[u8], key: &Key) -> Key {
+    let mut input = label.to_vec();
+    input.extend_from_slice(&key.0);
+    Key(sha256::hash(&input).0)
+}
+
+fn ratchet(key: &Key) -> Key {
+    derive(b"rekey", key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(rekey: Option<u64>) -> (Encrypt, Encrypt) {
+        let key = secretbox::gen_key();
+        let mut a = Encrypt::with_direction_keys(key.clone(), true);
+        let mut b = Encrypt::with_direction_keys(key, false);
+        if let Some(messages) = rekey {
+            a.set_rekey(messages, Duration::ZERO);
+            b.set_rekey(messages, Duration::ZERO);
+        }
+        (a, b)
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = secretbox::gen_key();
+        let (mut a, mut b) = (Encrypt::new(key.clone()), Encrypt::new(key.clone()));
+        let sealed = a.seal(b"hello").unwrap();
+        // the default format is a bare secretbox with the counter as nonce
+        assert_eq!(secretbox::open(&sealed, &get_nonce(1), &key).unwrap(), b"hello");
+        assert_eq!(b.open(&sealed).unwrap(), b"hello");
+        for rekey in [None, Some(0)] {
+            let (mut a, mut b) = pair(rekey);
+            for i in 0..10u8 {
+                assert_eq!(b.open(&a.seal(&[i]).unwrap()).unwrap(), [i]);
+                assert_eq!(a.open(&b.seal(&[i]).unwrap()).unwrap(), [i]);
+            }
+        }
+    }
+
+    #[test]
+    fn direction_keys_differ() {
+        let (mut a, mut b) = pair(None);
+        let sealed = a.seal(b"x").unwrap();
+        // a frame reflected back to its sender does not open
+        assert!(a.open(&sealed).is_err());
+        assert!(b.open(&sealed).is_ok());
+    }
+
+    #[test]
+    fn replay() {
+        let (mut a, mut b) = pair(Some(0));
+        let first = a.seal(b"1").unwrap();
+        b.open(&first).unwrap();
+        assert_eq!(b.open(&first), Err(CryptoError::Replay { expected: 2, got: 1 }));
+        // any error closes the session
+        assert_eq!(b.open(&a.seal(b"2").unwrap()), Err(CryptoError::Closed));
+    }
+
+    #[test]
+    fn out_of_order() {
+        let (mut a, mut b) = pair(Some(0));
+        let first = a.seal(b"1").unwrap();
+        let second = a.seal(b"2").unwrap();
+        assert_eq!(b.open(&second), Err(CryptoError::OutOfOrder { expected: 1, got: 2 }));
+        assert!(b.is_failed());
+        assert_eq!(b.open(&first), Err(CryptoError::Closed));
+    }
+
+    #[test]
+    fn replay_without_rekey() {
+        let key = secretbox::gen_key();
+        let (mut a, mut b) = (Encrypt::new(key.clone()), Encrypt::new(key));
+        let first = a.seal(b"1").unwrap();
+        b.open(&first).unwrap();
+        assert_eq!(b.open(&first), Err(CryptoError::Decrypt { seqnum: 2 }));
+    }
+
+    #[test]
+    fn across_rekey() {
+        let (mut a, mut b) = pair(Some(3));
+        let key = a.send_key.clone();
+        for i in 0..10u8 {
+            assert_eq!(b.open(&a.seal(&[i]).unwrap()).unwrap(), [i]);
+        }
+        assert_ne!(a.send_key.0, key.0);
+        assert_eq!(a.send_key.0, b.recv_key.0);
+        // the counter restarts under every new key
+        assert_eq!(a.send_seqnum, 1);
+        // the other direction keeps its key until its own rekey is due
+        assert_eq!(a.recv_key.0, b.send_key.0);
+        assert_eq!(b.open(&a.seal(b"x").unwrap()).unwrap(), b"x");
+    }
+
+    #[test]
+    fn stale_key_after_rekey() {
+        let (mut a, mut b) = pair(Some(1));
+        let mut old = Encrypt::with_keys(a.send_key.clone(), a.send_key.clone());
+        old.set_rekey(0, Duration::ZERO);
+        b.open(&a.seal(b"1").unwrap()).unwrap();
+        // a frame sealed under the retired key does not open
+        assert!(matches!(
+            b.open(&old.seal(b"2").unwrap()),
+            Err(CryptoError::Decrypt { .. })
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+//! The controlled side of a file transfer connection. Everything it sends
+//! goes through a `send_queue::PriorityQueue` drained by the same loop that
+//! reads from the stream, so listings and confirmations are not stuck behind
+//! file blocks, and the write of every block is timed for the adaptive
+//! compression level of its job.
+
+use crate::fs::{self, TransferJob};
+use crate::message::{
+    file_action, file_response, message, CompressCodec, FileAction, FileResponse, Message,
+};
+use crate::send_queue::{self, Priority, PrioritySender};
+use crate::tcp::FramedStream;
+use crate::ResultType;
+use protobuf::Message as _;
+use std::time::{Duration, Instant};
+use tokio::time::{interval, interval_at, Interval};
+
+// read jobs are polled this often while there are any, like in RustDesk
+const READ_JOBS_INTERVAL: Duration = Duration::from_millis(1);
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+
+struct Session {
+    tx: PrioritySender,
+    read_jobs: Vec<TransferJob>,
+    write_jobs: Vec<TransferJob>,
+    compress_codec: CompressCodec,
+    timer: Interval,
+}
+
+fn idle_timer() -> Interval {
+    interval_at(tokio::time::Instant::now() + IDLE_INTERVAL, IDLE_INTERVAL)
+}
+
+/// Serves the file actions of the peer on a logged in `stream` until it is
+/// closed. `compress_codec` is the one picked at login, see `codec::accept`.
+pub async fn serve(stream: &mut FramedStream, compress_codec: CompressCodec) -> ResultType<()> {
+    let (tx, mut queue) = send_queue::new();
+    let mut session = Session {
+        tx,
+        read_jobs: Vec::new(),
+        write_jobs: Vec::new(),
+        compress_codec,
+        timer: idle_timer(),
+    };
+    loop {
+        // every request and job round queues at most a few control messages,
+        // so stop taking new ones while that class is full instead of
+        // blocking the loop that drains it
+        let can_queue = session.tx.has_capacity(Priority::Control);
+        tokio::select! {
+            res = stream.next(), if can_queue => match res {
+                Some(Ok(bytes)) => session.on_message(Message::parse_from_bytes(&bytes)?).await?,
+                Some(Err(err)) => return Err(err.into()),
+                None => return Ok(()),
+            },
+            Some(queued) = queue.next() => {
+                let start = Instant::now();
+                stream.send_raw(queued.bytes).await?;
+                if let Some(job) = queued.job.and_then(|id| fs::get_job(id, &mut session.read_jobs)) {
+                    job.on_block_sent(start.elapsed());
+                }
+            }
+            _ = session.timer.tick(), if can_queue => {
+                if session.read_jobs.is_empty() {
+                    session.timer = idle_timer();
+                } else {
+                    fs::handle_read_jobs(&mut session.read_jobs, &mut session.tx).await?;
+                }
+            }
+        }
+    }
+}
+
+impl Session {
+    async fn on_message(&mut self, msg: Message) -> ResultType<()> {
+        match msg.union {
+            Some(message::Union::FileAction(fa)) => self.on_file_action(fa).await,
+            Some(message::Union::FileResponse(fr)) => self.on_file_response(fr).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn on_file_action(&mut self, fa: FileAction) -> ResultType<()> {
+        match fa.union {
+            Some(file_action::Union::ReadDir(rd)) => {
+                let msg = match fs::read_dir(&fs::get_path(&rd.path), rd.include_hidden) {
+                    Ok(dir) => {
+                        let mut resp = FileResponse::new();
+                        resp.set_dir(dir);
+                        let mut msg = Message::new();
+                        msg.set_file_response(resp);
+                        msg
+                    }
+                    Err(err) => fs::new_error(0, err, -1),
+                };
+                self.tx.send(&msg).await?;
+            }
+            Some(file_action::Union::AllFiles(f)) => {
+                let msg = match fs::get_recursive_files(&f.path, f.include_hidden) {
+                    Ok(files) => fs::new_dir(f.id, f.path, files),
+                    Err(err) => fs::new_error(f.id, err, -1),
+                };
+                self.tx.send(&msg).await?;
+            }
+            Some(file_action::Union::Send(s)) => {
+                match TransferJob::new_read(
+                    s.id,
+                    "".to_owned(),
+                    s.path,
+                    s.file_num,
+                    s.include_hidden,
+                    false,
+                    false,
+                    self.compress_codec,
+                ) {
+                    Ok(job) => {
+                        self.read_jobs.push(job);
+                        self.timer = interval(READ_JOBS_INTERVAL);
+                    }
+                    Err(err) => self.tx.send(&fs::new_error(s.id, err, 0)).await?,
+                }
+            }
+            Some(file_action::Union::Receive(r)) => {
+                self.write_jobs.push(TransferJob::new_write(
+                    r.id,
+                    "".to_owned(),
+                    r.path,
+                    r.file_num,
+                    false,
+                    false,
+                    r.files,
+                    false,
+                ));
+            }
+            Some(file_action::Union::Create(c)) => {
+                self.send_result(c.id, 0, fs::create_dir(&c.path)).await?;
+            }
+            Some(file_action::Union::RemoveFile(r)) => {
+                self.send_result(r.id, r.file_num, fs::remove_file(&r.path)).await?;
+            }
+            Some(file_action::Union::RemoveDir(r)) => {
+                let path = fs::get_path(&r.path);
+                let res = if r.recursive {
+                    fs::remove_all_empty_dir(&path)
+                } else {
+                    std::fs::remove_dir(&path).map_err(|err| err.into())
+                };
+                self.send_result(r.id, 0, res).await?;
+            }
+            Some(file_action::Union::Cancel(c)) => {
+                if let Some(job) = fs::get_job(c.id, &mut self.write_jobs) {
+                    job.remove_download_file();
+                }
+                fs::remove_job(c.id, &mut self.write_jobs);
+                fs::remove_job(c.id, &mut self.read_jobs);
+            }
+            Some(file_action::Union::SendConfirm(r)) => {
+                if let Some(job) = fs::get_job(r.id, &mut self.read_jobs) {
+                    job.confirm(&r);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Blocks, completion and errors of the files the peer uploads.
+    async fn on_file_response(&mut self, fr: FileResponse) -> ResultType<()> {
+        match fr.union {
+            Some(file_response::Union::Block(block)) => {
+                let (id, file_num) = (block.id, block.file_num);
+                if let Some(job) = fs::get_job(id, &mut self.write_jobs) {
+                    if let Err(err) = job.write(block).await {
+                        job.remove_download_file();
+                        fs::remove_job(id, &mut self.write_jobs);
+                        self.tx.send(&fs::new_error(id, err, file_num)).await?;
+                    }
+                }
+            }
+            Some(file_response::Union::Done(d)) => {
+                if let Some(job) = fs::get_job(d.id, &mut self.write_jobs) {
+                    job.modify_time();
+                }
+                fs::remove_job(d.id, &mut self.write_jobs);
+            }
+            Some(file_response::Union::Error(e)) => {
+                if let Some(job) = fs::get_job(e.id, &mut self.write_jobs) {
+                    job.remove_download_file();
+                }
+                fs::remove_job(e.id, &mut self.write_jobs);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn send_result(&mut self, id: i32, file_num: i32, res: ResultType<()>) -> ResultType<()> {
+        let msg = match res {
+            Ok(()) => fs::new_done(id, file_num),
+            Err(err) => fs::new_error(id, err, file_num),
+        };
+        self.tx.send(&msg).await
+    }
+}
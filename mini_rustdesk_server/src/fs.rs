@@ -1,14 +1,15 @@
+#[cfg(windows)]
 use std::os::windows::prelude::*;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use crate::ResultType;
 use serde_derive::{Deserialize, Serialize};
 use tokio::{fs::File, io::*};
 use crate::{
     message::*,
-    compress::{compress, decompress},
-    config::COMPRESS_LEVEL,
+    codec,
 };
+use crate::send_queue::MessageSink;
 use crate::tcp;
 
 pub type Stream = tcp::FramedStream;
@@ -18,6 +19,7 @@ pub fn read_dir(path: &Path, include_hidden: bool) -> ResultType<FileDirectory>
         path: get_string(path),
         ..Default::default()
     };
+    #[cfg(windows)]
     if "/" == &get_string(path) {
         let drives = unsafe { winapi::um::fileapi::GetLogicalDrives() };
         for i in 0..32 {
@@ -52,9 +54,14 @@ pub fn read_dir(path: &Path, include_hidden: bool) -> ResultType<FileDirectory>
         } else {
             continue;
         }
+        #[cfg(windows)]
         if meta.file_attributes() & 0x2 != 0 {
             is_hidden = true;
         }
+        #[cfg(not(windows))]
+        if name.starts_with('.') {
+            is_hidden = true;
+        }
         if is_hidden && !include_hidden {
             continue;
         }
@@ -175,11 +182,11 @@ pub fn get_recursive_files(path: &str, include_hidden: bool) -> ResultType<Vec<F
 
 #[inline]
 pub fn is_file_exists(file_path: &str) -> bool {
-    return Path::new(file_path).exists();
+    Path::new(file_path).exists()
 }
 
 #[inline]
-pub fn can_enable_overwrite_detection(version: i64) -> bool {
+pub fn can_enable_overwrite_detection(_version: i64) -> bool {
     true
 }
 
@@ -204,6 +211,9 @@ pub struct TransferJob {
     file_skipped: bool,
     file_is_waiting: bool,
     default_overwrite_strategy: Option<bool>,
+    encoder: codec::Encoder,
+    // how long the block being sent took to compress
+    compress_elapsed: Option<Duration>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -282,6 +292,8 @@ impl TransferJob {
         }
     }
 
+    /// `compress_codec` is the one negotiated at login, see `codec::accept`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new_read(
         id: i32,
         remote: String,
@@ -290,6 +302,7 @@ impl TransferJob {
         show_hidden: bool,
         is_remote: bool,
         enable_overwrite_detection: bool,
+        compress_codec: CompressCodec,
     ) -> ResultType<Self> {
         log::info!("new read {}", path);
         let files = get_recursive_files(&path, show_hidden)?;
@@ -304,6 +317,7 @@ impl TransferJob {
             files,
             total_size,
             enable_overwrite_detection,
+            encoder: codec::Encoder::new(compress_codec),
             ..Default::default()
         })
     }
@@ -391,7 +405,7 @@ impl TransferJob {
             self.file = Some(File::create(&path).await?);
         }
         if block.compressed {
-            let tmp = decompress(&block.data);
+            let tmp = codec::decode(block.codec.enum_value_or_default(), &block.data)?;
             self.file.as_mut().unwrap().write_all(&tmp).await?;
             self.finished_size += tmp.len() as u64;
         } else {
@@ -411,7 +425,10 @@ impl TransferJob {
         }
     }
 
-    pub async fn read(&mut self, stream: &mut Stream) -> ResultType<Option<FileTransferBlock>> {
+    pub async fn read(
+        &mut self,
+        stream: &mut impl MessageSink,
+    ) -> ResultType<Option<FileTransferBlock>> {
         let file_num = self.file_num as usize;
         if file_num >= self.files.len() {
             self.file.take();
@@ -470,11 +487,12 @@ impl TransferJob {
         } else {
             self.finished_size += offset as u64;
             if !is_compressed_file(name) {
-                let tmp = compress(&buf, COMPRESS_LEVEL);
-                if tmp.len() < buf.len() {
+                let start = Instant::now();
+                if let Some(tmp) = self.encoder.encode(&buf) {
                     buf = tmp;
                     compressed = true;
                 }
+                self.compress_elapsed = Some(start.elapsed());
             }
             self.transferred += buf.len() as u64;
         }
@@ -483,11 +501,20 @@ impl TransferJob {
             file_num: file_num as _,
             data: buf.into(),
             compressed,
+            codec: self.encoder.codec().into(),
             ..Default::default()
         }))
     }
 
-    async fn send_current_digest(&mut self, stream: &mut Stream) -> ResultType<()> {
+    /// Feeds back how long the last block took to write to the stream, so
+    /// the encoder can weigh it against the time it took to compress.
+    pub fn on_block_sent(&mut self, send_elapsed: Duration) {
+        if let Some(compress_elapsed) = self.compress_elapsed.take() {
+            self.encoder.update(compress_elapsed, send_elapsed);
+        }
+    }
+
+    async fn send_current_digest(&mut self, stream: &mut impl MessageSink) -> ResultType<()> {
         let mut msg = Message::new();
         let mut resp = FileResponse::new();
         let meta = self.file.as_ref().unwrap().metadata().await?;
@@ -503,7 +530,7 @@ impl TransferJob {
             ..Default::default()
         });
         msg.set_file_response(resp);
-        stream.send(&msg).await?;
+        stream.send_message(&msg).await?;
         log::info!(
             "id: {}, file_num:{}, digest message is sent. waiting for confirm. msg: {:?}",
             self.id,
@@ -718,34 +745,40 @@ pub fn get_job(id: i32, jobs: &mut [TransferJob]) -> Option<&mut TransferJob> {
     jobs.iter_mut().find(|x| x.id() == id)
 }
 
+/// Sends the next block of every job. With a `PrioritySender` as `stream`,
+/// jobs are skipped while its bulk class is full, so that the caller's loop
+/// keeps going and the blocks wait for more urgent traffic. The task draining
+/// the queue then reports the write time of each block with
+/// `TransferJob::on_block_sent`.
 pub async fn handle_read_jobs(
     jobs: &mut Vec<TransferJob>,
-    stream: &mut Stream,
+    stream: &mut impl MessageSink,
 ) -> ResultType<()> {
     let mut finished = Vec::new();
     for job in jobs.iter_mut() {
-        if job.is_last_job {
+        if job.is_last_job || !stream.can_send_bulk() {
             continue;
         }
         match job.read(stream).await {
             Err(err) => {
                 stream
-                    .send(&new_error(job.id(), err, job.file_num()))
+                    .send_message(&new_error(job.id(), err, job.file_num()))
                     .await?;
             }
             Ok(Some(block)) => {
-                stream.send(&new_block(block)).await?;
+                if let Some(elapsed) = stream.send_block(&new_block(block), job.id()).await? {
+                    job.on_block_sent(elapsed);
+                }
             }
             Ok(None) => {
                 if job.job_completed() {
                     finished.push(job.id());
-                    let err = job.job_error();
-                    if err.is_some() {
+                    if let Some(err) = job.job_error() {
                         stream
-                            .send(&new_error(job.id(), err.unwrap(), job.file_num()))
+                            .send_message(&new_error(job.id(), err, job.file_num()))
                             .await?;
                     } else {
-                        stream.send(&new_done(job.id(), job.file_num())).await?;
+                        stream.send_message(&new_done(job.id(), job.file_num())).await?;
                     }
                 } else {
                     // waiting confirmation.
@@ -0,0 +1,128 @@
+use crate::message::{message, IdPk, Message, PublicKey, SignedId};
+use crate::tcp::FramedStream;
+use crate::ResultType;
+use anyhow::bail;
+use protobuf::Message as _;
+use sodiumoxide::crypto::{box_, secretbox, sign};
+use std::time::Duration;
+
+const HANDSHAKE_TIMEOUT: u64 = 10_000;
+// once agreed, both sides switch to rekeyed frames right after the key exchange
+const REKEY_MESSAGES: u64 = 1 << 20;
+const REKEY_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Each side uses a fresh box key pair per connection, so a zero nonce is
+// never reused with the same key.
+fn zero_nonce() -> box_::Nonce {
+    box_::Nonce([0u8; box_::NONCEBYTES])
+}
+
+async fn next_message(stream: &mut FramedStream) -> ResultType<message::Union> {
+    match stream.next_timeout(HANDSHAKE_TIMEOUT).await {
+        Some(Ok(bytes)) => match Message::parse_from_bytes(&bytes)?.union {
+            Some(union) => Ok(union),
+            None => bail!("Empty handshake message"),
+        },
+        Some(Err(err)) => Err(err.into()),
+        None => bail!("Handshake timeout"),
+    }
+}
+
+/// Enables encryption on `stream` with the extensions both sides agreed on,
+/// or the plain single key format any RustDesk peer speaks.
+fn set_key(
+    stream: &mut FramedStream,
+    key: secretbox::Key,
+    initiator: bool,
+    direction_keys: bool,
+    rekey: bool,
+) {
+    if direction_keys {
+        stream.set_direction_keys(key, initiator);
+    } else {
+        stream.set_key(key);
+    }
+    if rekey {
+        stream.set_rekey(REKEY_MESSAGES, REKEY_INTERVAL);
+    }
+}
+
+/// The connecting side. Waits for the peer's `SignedId`, checks that it is
+/// signed by `pk` and belongs to `id`, then sends a fresh symmetric key sealed
+/// to the peer's box key, accepting the extensions the peer offered, and
+/// enables encryption on `stream`.
+pub async fn initiate(stream: &mut FramedStream, id: &str, pk: &sign::PublicKey) -> ResultType<()> {
+    let signed_id = match next_message(stream).await? {
+        message::Union::SignedId(signed_id) => signed_id,
+        _ => bail!("Expected SignedId"),
+    };
+    let id_pk = match sign::verify(&signed_id.id, pk) {
+        Ok(bytes) => IdPk::parse_from_bytes(&bytes)?,
+        Err(()) => bail!("Invalid signature of peer id"),
+    };
+    if id_pk.id != id {
+        bail!("Peer id mismatch, expected {}, got {}", id, id_pk.id);
+    }
+    let their_pk = match box_::PublicKey::from_slice(&id_pk.pk) {
+        Some(pk) => pk,
+        None => bail!("Invalid peer box key"),
+    };
+    let (our_pk, our_sk) = box_::gen_keypair();
+    let key = secretbox::gen_key();
+    let sealed = box_::seal(&key.0, &zero_nonce(), &their_pk, &our_sk);
+    let mut msg = Message::new();
+    msg.set_public_key(PublicKey {
+        asymmetric_value: our_pk.0.to_vec().into(),
+        symmetric_value: sealed.into(),
+        direction_keys: id_pk.direction_keys,
+        rekey: id_pk.rekey,
+        ..Default::default()
+    });
+    stream.send(&msg).await?;
+    set_key(stream, key, true, id_pk.direction_keys, id_pk.rekey);
+    Ok(())
+}
+
+/// The accepting side. Sends our `id` with a fresh box key signed by `sk`,
+/// offering direction keys and rekeying, then opens the symmetric key sent by
+/// the initiator and enables encryption on `stream` with the extensions it
+/// accepted.
+pub async fn respond(stream: &mut FramedStream, id: &str, sk: &sign::SecretKey) -> ResultType<()> {
+    let (our_pk, our_sk) = box_::gen_keypair();
+    let id_pk = IdPk {
+        id: id.to_owned(),
+        pk: our_pk.0.to_vec().into(),
+        direction_keys: true,
+        rekey: true,
+        ..Default::default()
+    };
+    let mut msg = Message::new();
+    msg.set_signed_id(SignedId {
+        id: sign::sign(&id_pk.write_to_bytes()?, sk).into(),
+        ..Default::default()
+    });
+    stream.send(&msg).await?;
+    let public_key = match next_message(stream).await? {
+        message::Union::PublicKey(public_key) => public_key,
+        _ => bail!("Expected PublicKey"),
+    };
+    let their_pk = match box_::PublicKey::from_slice(&public_key.asymmetric_value) {
+        Some(pk) => pk,
+        None => bail!("Invalid peer box key"),
+    };
+    let key = match box_::open(
+        &public_key.symmetric_value,
+        &zero_nonce(),
+        &their_pk,
+        &our_sk,
+    ) {
+        Ok(key) => key,
+        Err(()) => bail!("Failed to open symmetric key"),
+    };
+    let key = match secretbox::Key::from_slice(&key) {
+        Some(key) => key,
+        None => bail!("Invalid symmetric key"),
+    };
+    set_key(stream, key, false, public_key.direction_keys, public_key.rekey);
+    Ok(())
+}
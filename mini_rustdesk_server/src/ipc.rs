@@ -0,0 +1,147 @@
+//! Local control over a unix domain socket, framed with `BytesCodec` like
+//! `FramedStream`. Every frame is one json `Command` or `Response`. There is
+//! no further authentication: the socket is created with mode 0600 and only
+//! connections from the socket owner's uid are served.
+
+use crate::bytes_codec::BytesCodec;
+use crate::ResultType;
+use anyhow::bail;
+use bytes::{Bytes, BytesMut};
+use futures::{SinkExt, StreamExt};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    io::{Error, ErrorKind},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+};
+use tokio::net::{UnixListener, UnixStream};
+use tokio_util::codec::Framed;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Command {
+    Status,
+    Kick { uuid: String },
+    Config { name: String, value: String },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub error: String,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub data: serde_json::Value,
+}
+
+impl Response {
+    pub fn ok(data: serde_json::Value) -> Self {
+        Self {
+            ok: true,
+            data,
+            ..Default::default()
+        }
+    }
+
+    pub fn err(error: impl ToString) -> Self {
+        Self {
+            error: error.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct IpcStream(Framed<UnixStream, BytesCodec>);
+
+impl IpcStream {
+    pub async fn connect(path: &str) -> ResultType<Self> {
+        Ok(Self(Framed::new(
+            UnixStream::connect(path).await?,
+            BytesCodec::new(),
+        )))
+    }
+
+    pub async fn send(&mut self, msg: &impl serde::Serialize) -> ResultType<()> {
+        self.0.send(Bytes::from(serde_json::to_vec(msg)?)).await?;
+        Ok(())
+    }
+
+    pub async fn next(&mut self) -> Option<Result<BytesMut, Error>> {
+        self.0.next().await
+    }
+}
+
+/// Sends one command to the server listening on `path` and waits for the
+/// answer, for a local cli or gui.
+pub async fn request(path: &str, cmd: &Command) -> ResultType<Response> {
+    let mut stream = IpcStream::connect(path).await?;
+    stream.send(cmd).await?;
+    match stream.next().await {
+        Some(Ok(bytes)) => Ok(serde_json::from_slice(&bytes)?),
+        Some(Err(err)) => Err(err.into()),
+        None => bail!("Connection closed"),
+    }
+}
+
+/// Creates the socket at `path`, replacing a stale one left by a crashed
+/// process, but refusing to take over one that is still served.
+pub async fn listen(path: &str) -> ResultType<UnixListener> {
+    if Path::new(path).exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            bail!("Ipc socket {} is in use", path);
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Serves `handler` on `path` until the listener fails.
+pub async fn serve<F, Fut>(path: &str, handler: F) -> ResultType<()>
+where
+    F: Fn(Command) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send,
+{
+    let listener = listen(path).await?;
+    let owner = std::fs::metadata(path)?.uid();
+    log::info!("Listening on ipc {}", path);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        match stream.peer_cred() {
+            Ok(cred) if cred.uid() == owner => {}
+            Ok(cred) => {
+                log::warn!("Ipc connection from uid {} refused", cred.uid());
+                continue;
+            }
+            Err(err) => {
+                log::warn!("Failed to get ipc peer credentials: {}", err);
+                continue;
+            }
+        }
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream, handler).await {
+                log::debug!("Ipc connection closed: {}", err);
+            }
+        });
+    }
+}
+
+async fn handle<F, Fut>(stream: UnixStream, handler: F) -> ResultType<()>
+where
+    F: Fn(Command) -> Fut,
+    Fut: Future<Output = Response>,
+{
+    let mut stream = IpcStream(Framed::new(stream, BytesCodec::new()));
+    while let Some(res) = stream.next().await {
+        let bytes = res?;
+        let res = match serde_json::from_slice::<Command>(&bytes) {
+            Ok(cmd) => handler(cmd).await,
+            Err(err) => Response::err(Error::new(ErrorKind::InvalidData, err)),
+        };
+        stream.send(&res).await?;
+    }
+    Ok(())
+}
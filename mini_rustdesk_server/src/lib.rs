@@ -0,0 +1,33 @@
+
+pub mod config;
+#[macro_use]
+pub mod common;
+pub mod database;
+mod pg_database;
+pub mod peer;
+pub mod protos;
+pub use protos::*;
+pub mod bytes_codec;
+pub mod compress;
+pub mod codec;
+pub mod fs;
+pub mod file_transfer;
+pub mod tcp;
+pub mod udp;
+mod metrics;
+pub mod rudp;
+pub mod tls;
+pub mod quic;
+pub mod handshake;
+pub mod encrypt;
+pub mod send_queue;
+pub mod proxy;
+pub mod punch;
+pub mod proxy_protocol;
+#[cfg(unix)]
+pub mod ipc;
+pub mod relay_server;
+mod rendezvous_server;
+pub use rendezvous_server::RendezvousServer;
+
+pub type ResultType<F, E = anyhow::Error> = anyhow::Result<F, E>;
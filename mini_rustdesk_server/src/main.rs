@@ -1,26 +1,9 @@
-
 use flexi_logger::*;
-mod config;
-use config::RENDEZVOUS_PORT;
-#[macro_use]
-mod common;
-mod database;
-mod peer;
-mod protos;
-pub use protos::*;
-mod bytes_codec;
-mod compress;
-mod fs;
-mod tcp;
-mod udp;
-mod relay_server;
-mod rendezvous_server;
-use rendezvous_server::RendezvousServer;
-use crate::common::{init_args,get_arg_or};
+use mini_rustdesk_server::common::{get_arg_or, init_args};
+use mini_rustdesk_server::config::RENDEZVOUS_PORT;
+use mini_rustdesk_server::{RendezvousServer, ResultType};
 use anyhow::{Error, Ok};
 
-pub type ResultType<F, E = anyhow::Error> = anyhow::Result<F, E>;
-
 fn main() -> ResultType<()> {
     let _logger = Logger::try_with_env_or_str("info")?
         .log_to_stdout()
@@ -29,9 +12,10 @@ fn main() -> ResultType<()> {
         .start()?;
     let args = format!(
         "-p, --port=[NUMBER(default={RENDEZVOUS_PORT})] 'Sets the listening port'
-        -R, --rendezvous-servers=[HOSTS] 'Sets rendezvous servers, separated by comma'
         -r, --relay-servers=[HOST] 'Sets the default relay servers, separated by comma'
-        -k, --key=[KEY] 'Only allow the client with the same key'",
+        -k, --key=[KEY] 'Only allow the client with the same key'
+        --db-url=[URL] 'Sets the database, a sqlite file path, a postgres:// url or memory'
+        --ipc-path=[PATH] 'Serves local control commands on this unix socket'",
     );
     init_args(&args, "hbbs", "RustDesk ID/Rendezvous Server");
     let port = get_arg_or("port", RENDEZVOUS_PORT.to_string()).parse::<i32>()?;
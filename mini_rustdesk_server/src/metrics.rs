@@ -0,0 +1,99 @@
+use crate::message::TestDelay;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Counters of one connection, kept by `FramedStream` and `FramedSocket`.
+/// Byte counts are what went over the wire, i.e. after encryption.
+#[derive(Debug)]
+pub struct Metrics {
+    created: Instant,
+    bytes_sent: u64,
+    bytes_received: u64,
+    messages_sent: u64,
+    messages_received: u64,
+    encryption_failures: u64,
+    send_timeouts: u64,
+    rtt: Option<Duration>,
+}
+
+/// A point-in-time copy of `Metrics`.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub uptime: Duration,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub encryption_failures: u64,
+    pub send_timeouts: u64,
+    /// Smoothed round trip time, if any `TestDelay` was exchanged.
+    pub rtt: Option<Duration>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            bytes_sent: 0,
+            bytes_received: 0,
+            messages_sent: 0,
+            messages_received: 0,
+            encryption_failures: 0,
+            send_timeouts: 0,
+            rtt: None,
+        }
+    }
+}
+
+impl Metrics {
+    #[inline]
+    pub fn on_sent(&mut self, bytes: usize) {
+        self.bytes_sent += bytes as u64;
+        self.messages_sent += 1;
+    }
+
+    #[inline]
+    pub fn on_received(&mut self, bytes: usize) {
+        self.bytes_received += bytes as u64;
+        self.messages_received += 1;
+    }
+
+    #[inline]
+    pub fn on_encryption_failure(&mut self) {
+        self.encryption_failures += 1;
+    }
+
+    #[inline]
+    pub fn on_send_timeout(&mut self) {
+        self.send_timeouts += 1;
+    }
+
+    /// Feeds a `TestDelay` the peer echoed back, `time` being the ms
+    /// timestamp we put into it. Samples are smoothed as in RFC 6298.
+    pub fn on_test_delay(&mut self, td: &TestDelay) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_millis() as i64)
+            .unwrap_or_default();
+        if td.time <= 0 || td.time > now {
+            return;
+        }
+        let sample = Duration::from_millis((now - td.time) as _);
+        self.rtt = Some(match self.rtt {
+            Some(rtt) => (rtt * 7 + sample) / 8,
+            None => sample,
+        });
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            uptime: self.created.elapsed(),
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            messages_sent: self.messages_sent,
+            messages_received: self.messages_received,
+            encryption_failures: self.encryption_failures,
+            send_timeouts: self.send_timeouts,
+            rtt: self.rtt,
+        }
+    }
+}
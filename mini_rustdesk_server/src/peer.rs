@@ -1,37 +1,37 @@
 use crate::common::*;
-use crate::database;
+use crate::database::{self, PeerStore};
 use bytes::Bytes;
 use crate::rendezvous::*;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::RwLock;
 use crate::ResultType;
 use serde_derive::{Deserialize, Serialize};
-use std::{collections::HashMap, collections::HashSet, net::SocketAddr, sync::Arc, time::Instant};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
+    time::Instant,
+};
 
-type UserStatusMap = HashMap<Vec<u8>, Arc<(Option<Vec<u8>>, bool)>>;
-type IpChangesMap = HashMap<String, (Instant, HashMap<String, i32>)>;
-lazy_static::lazy_static! {
-    pub(crate) static ref USER_STATUS: RwLock<UserStatusMap> = Default::default();
-    pub(crate) static ref IP_CHANGES: Mutex<IpChangesMap> = Default::default();
-}
 pub static IP_CHANGE_DUR: u64 = 180;
 pub static IP_CHANGE_DUR_X2: u64 = IP_CHANGE_DUR * 2;
 pub static DAY_SECONDS: u64 = 3600 * 24;
 pub static IP_BLOCK_DUR: u64 = 60;
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub(crate) struct PeerInfo {
+pub struct PeerInfo {
     #[serde(default)]
-    pub(crate) ip: String,
+    pub ip: String,
 }
 
-pub(crate) struct Peer {
-    pub(crate) socket_addr: SocketAddr,
-    pub(crate) last_reg_time: Instant,
-    pub(crate) guid: Vec<u8>,
-    pub(crate) uuid: Bytes,
-    pub(crate) pk: Bytes,
-    pub(crate) info: PeerInfo,
-    pub(crate) reg_pk: (u32, Instant), // how often register_pk
+pub struct Peer {
+    pub socket_addr: SocketAddr,
+    pub last_reg_time: Instant,
+    pub guid: Vec<u8>,
+    pub uuid: Bytes,
+    pub pk: Bytes,
+    pub info: PeerInfo,
+    pub reg_pk: (u32, Instant), // how often register_pk
 }
 
 impl Default for Peer {
@@ -48,101 +48,301 @@ impl Default for Peer {
     }
 }
 
-pub(crate) type LockPeer = Arc<RwLock<Peer>>;
+pub type LockPeer = Arc<RwLock<Peer>>;
 
-#[derive(Clone)]
-pub(crate) struct PeerMap {
-    pub(crate) db: database::Database,
+const DEFAULT_CACHE_SIZE: usize = 100_000;
+const DEFAULT_CACHE_TTL: u64 = 3600; // in seconds
+const EVICT_INTERVAL: u64 = 60; // in seconds
+// a full cache drops this share of its peers at once
+const EVICT_BATCH_DIVISOR: usize = 16;
+
+struct CacheEntry {
+    peer: LockPeer,
+    loaded: u64,            // in ms since PeerCache::epoch
+    last_access: AtomicU64, // in ms since PeerCache::epoch
 }
 
-impl PeerMap {
-    pub(crate) async fn new() -> ResultType<Self> {
-        let exe_path_result = std::env::current_exe();
-        let db_path = match exe_path_result {
-            Ok(exe_path) => exe_path.with_file_name("db_v2.sqlite3"),
-            Err(e) => {
-                log::error!("Failed to get current executable path: {}", e);
-                std::path::PathBuf::from("db_v2.sqlite3")
+/// Live peers by id, plus a guid index for `get_peer_by_guid`. Entries expire
+/// by the time they were loaded from or written to the database, a full
+/// cache drops the least recently looked up ones.
+struct PeerCache {
+    peers: HashMap<String, CacheEntry>,
+    guids: HashMap<Vec<u8>, String>,
+    epoch: Instant,
+}
+
+impl Default for PeerCache {
+    fn default() -> Self {
+        Self {
+            peers: Default::default(),
+            guids: Default::default(),
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl PeerCache {
+    #[inline]
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as _
+    }
+
+    fn get(&self, id: &str) -> Option<LockPeer> {
+        let entry = self.peers.get(id)?;
+        entry.last_access.store(self.now(), Ordering::Relaxed);
+        Some(entry.peer.clone())
+    }
+
+    fn get_by_guid(&self, guid: &[u8]) -> Option<LockPeer> {
+        self.get(self.guids.get(guid)?)
+    }
+
+    fn insert(&mut self, id: String, peer: LockPeer, guid: Vec<u8>) {
+        if !guid.is_empty() {
+            self.guids.insert(guid, id.clone());
+        }
+        let now = self.now();
+        self.peers.insert(
+            id,
+            CacheEntry {
+                peer,
+                loaded: now,
+                last_access: AtomicU64::new(now),
+            },
+        );
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(entry) = self.peers.remove(id) {
+            if let Ok(peer) = entry.peer.try_read() {
+                self.guids.remove(&peer.guid);
+            } else {
+                self.guids.retain(|_, x| x != id);
             }
-        };
+        }
+    }
 
-        let db_path_str = db_path.to_str().unwrap_or("db_v2.sqlite3");
-        log::info!("DB Path: {}", db_path_str);
+    /// Removes peers loaded more than `ttl` seconds ago, so that changes
+    /// other servers made to the database are picked up by the next lookup.
+    /// Peers that are locked right now are in use and kept.
+    fn evict(&mut self, ttl: u64) -> usize {
+        let now = self.now();
+        let expired: Vec<String> = self
+            .peers
+            .iter()
+            .filter(|(_, x)| {
+                now.saturating_sub(x.loaded) >= ttl * 1000
+                    && x.peer.try_read().is_ok()
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in expired.iter() {
+            self.remove(id);
+        }
+        expired.len()
+    }
+
+    /// Makes room in a full cache by dropping the least recently used
+    /// `capacity / EVICT_BATCH_DIVISOR` peers, so that the scan is paid once
+    /// per that many misses rather than on every one.
+    fn evict_lru(&mut self, capacity: usize) {
+        let n = (capacity / EVICT_BATCH_DIVISOR).clamp(1, self.peers.len());
+        let mut entries: Vec<(u64, &String)> = self
+            .peers
+            .iter()
+            .map(|(id, x)| (x.last_access.load(Ordering::Relaxed), id))
+            .collect();
+        entries.select_nth_unstable(n - 1);
+        let ids: Vec<String> = entries[..n].iter().map(|(_, id)| (*id).clone()).collect();
+        for id in ids.iter() {
+            self.remove(id);
+        }
+    }
+}
+
+/// Peers by id, cached in memory in front of the database. Cached peers are
+/// the live objects whose `socket_addr` and `last_reg_time` the rendezvous
+/// server updates, registrations are written through to the database. The
+/// cache holds at most `peer-cache-size` peers and drops those loaded from the
+/// database more than `peer-cache-ttl` seconds ago.
+#[derive(Clone)]
+pub struct PeerMap {
+    cache: Arc<RwLock<PeerCache>>,
+    capacity: usize,
+    pub db: Arc<dyn PeerStore>,
+}
+
+impl PeerMap {
+    pub async fn new() -> ResultType<Self> {
+        let db_url = get_arg_or("db-url", database::get_db_path());
+        log::info!("DB URL: {}", db_url);
+        let capacity = get_arg("peer-cache-size")
+            .parse::<usize>()
+            .unwrap_or(DEFAULT_CACHE_SIZE)
+            .max(1);
+        let ttl = get_arg("peer-cache-ttl")
+            .parse::<u64>()
+            .unwrap_or(DEFAULT_CACHE_TTL);
+        log::info!("PEER_CACHE_SIZE: {}, PEER_CACHE_TTL: {}s", capacity, ttl);
 
         let pm = Self {
-            db: database::Database::new(db_path_str).await?,
+            cache: Default::default(),
+            capacity,
+            db: database::open_peer_store(&db_url).await?,
         };
+        let cache = pm.cache.clone();
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(std::time::Duration::from_secs(EVICT_INTERVAL));
+            loop {
+                timer.tick().await;
+                let n = cache.write().await.evict(ttl);
+                if n > 0 {
+                    log::debug!("Evicted {} offline peers from cache", n);
+                }
+            }
+        });
         Ok(pm)
     }
 
-    pub(crate) async fn update_or_insert_peer(
+    async fn cache_peer(&self, id: String, peer: LockPeer, guid: Vec<u8>) -> LockPeer {
+        let mut cache = self.cache.write().await;
+        // another task may have loaded the same peer meanwhile
+        if let Some(existing) = cache.get(&id) {
+            return existing;
+        }
+        if cache.peers.len() >= self.capacity {
+            cache.evict_lru(self.capacity);
+        }
+        cache.insert(id, peer.clone(), guid);
+        peer
+    }
+
+    /// Writes a registered key through to the database, updating the peer
+    /// if it is stored and inserting it otherwise. The guid is always read
+    /// from the database rather than the cache, another server sharing the
+    /// database may have inserted or changed the peer meanwhile.
+    pub async fn update_or_insert_peer(
         &self,
         id: String,
         uuid: Bytes,
         pk: Bytes,
         ip: String,
     ) -> register_pk_response::Result {
-        let peer = self.db.get_peer_by_id(&id).await;
-        match peer {
-            Ok(Some(existing_peer)) => {
-                log::info!("Peer exists, updating...");
-                let info_str = serde_json::to_string(&existing_peer.info).unwrap_or_default();
-                if let Err(err) = self.db.update_pk_by_guid(&existing_peer.guid, &id, &pk, &info_str).await {
-                    log::error!("db.update_pk failed: {}", err);
-                    register_pk_response::Result::SERVER_ERROR
-                } else {
-                    log::info!("Peer updated successfully.");
-                    register_pk_response::Result::OK
-                }
-            },
-            Ok(None) => {
+        let stored = match self.db.get_peer_by_id(&id).await {
+            Ok(v) => v,
+            Err(err) => {
+                log::error!("Failed to get peer by ID: {}", err);
+                return register_pk_response::Result::SERVER_ERROR;
+            }
+        };
+        let stored = match stored {
+            Some(v) => v,
+            None => {
                 log::info!("Peer does not exist, inserting...");
-                let info_str = serde_json::to_string(&PeerInfo { ip }).unwrap_or_default();
+                let info = PeerInfo { ip };
+                let info_str = serde_json::to_string(&info).unwrap_or_default();
                 match self.db.insert_peer(&id, &uuid, &pk, &info_str).await {
                     Ok(guid) => {
                         log::info!("Peer inserted successfully with GUID: {:?}", guid);
-                        register_pk_response::Result::OK
-                    },
-                    Err(err) => {
-                        log::error!("db.insert_peer failed: {}", err);
-                        register_pk_response::Result::SERVER_ERROR
+                        self.refresh(id, guid, uuid, pk, info).await;
+                        return register_pk_response::Result::OK;
                     }
+                    // most likely inserted by another server meanwhile
+                    Err(err) => match self.db.get_peer_by_id(&id).await {
+                        Ok(Some(v)) => v,
+                        _ => {
+                            log::error!("db.insert_peer failed: {}", err);
+                            return register_pk_response::Result::SERVER_ERROR;
+                        }
+                    },
                 }
-            },
-            Err(err) => {
-                log::error!("Failed to get peer by ID: {}", err);
-                register_pk_response::Result::SERVER_ERROR
             }
+        };
+        log::info!("Peer exists, updating...");
+        if let Err(err) = self
+            .db
+            .update_pk_by_guid(&stored.guid, &id, &pk, &stored.info)
+            .await
+        {
+            log::error!("db.update_pk failed: {}", err);
+            return register_pk_response::Result::SERVER_ERROR;
         }
+        log::info!("Peer updated successfully.");
+        let info = serde_json::from_str::<PeerInfo>(&stored.info).unwrap_or_default();
+        self.refresh(id, stored.guid, stored.uuid.into(), pk, info).await;
+        register_pk_response::Result::OK
     }
 
-    pub(crate) async fn get_peer_by_id(&self, id: &str) -> Option<LockPeer> {
-         if let Ok(Some(v)) = self.db.get_peer_by_id(id).await {
-            let peer = Peer {
-                guid: v.guid,
-                uuid: v.uuid.into(),
-                pk: v.pk.into(),
-                info: serde_json::from_str::<PeerInfo>(&v.info).unwrap_or_default(),
-                ..Default::default()
-            };
-            let peer = Arc::new(RwLock::new(peer));
+    /// Brings the cached peer in line with what was just written to the
+    /// database, caching it if needed, and restarts its ttl.
+    async fn refresh(&self, id: String, guid: Vec<u8>, uuid: Bytes, pk: Bytes, info: PeerInfo) {
+        let peer = self.cache_peer(id.clone(), Default::default(), Vec::new()).await;
+        let old_guid = {
+            let mut peer = peer.write().await;
+            peer.uuid = uuid;
+            peer.pk = pk;
+            peer.info = info;
+            std::mem::replace(&mut peer.guid, guid.clone())
+        };
+        let mut cache = self.cache.write().await;
+        if old_guid != guid {
+            cache.guids.remove(&old_guid);
+        }
+        let now = cache.now();
+        if let Some(entry) = cache.peers.get_mut(&id) {
+            entry.loaded = now;
+        }
+        cache.guids.insert(guid, id);
+    }
+
+    pub async fn get_in_memory(&self, id: &str) -> Option<LockPeer> {
+        self.cache.read().await.get(id)
+    }
+
+    pub async fn is_in_memory(&self, id: &str) -> bool {
+        self.cache.read().await.peers.contains_key(id)
+    }
+
+    /// The cached peer, loaded from the database if needed, or a new empty
+    /// one that is cached as well, for a peer registering for the first time.
+    pub async fn get_or(&self, id: &str) -> LockPeer {
+        if let Some(peer) = self.get_peer_by_id(id).await {
+            return peer;
+        }
+        self.cache_peer(id.to_owned(), Default::default(), Vec::new())
+            .await
+    }
+
+    pub async fn get_peer_by_id(&self, id: &str) -> Option<LockPeer> {
+        if let Some(peer) = self.get_in_memory(id).await {
             return Some(peer);
         }
+        if let Ok(Some(v)) = self.db.get_peer_by_id(id).await {
+            return Some(self.cache_loaded(v).await);
+        }
         None
     }
 
-    pub(crate) async fn get_peer_by_guid(&self, guid:&[u8]) -> Option<LockPeer> {
+    pub async fn get_peer_by_guid(&self, guid: &[u8]) -> Option<LockPeer> {
+        if let Some(peer) = self.cache.read().await.get_by_guid(guid) {
+            return Some(peer);
+        }
         if let Ok(Some(v)) = self.db.get_peer_by_guid(guid).await {
-           let peer = Peer {
-               guid: v.guid,
-               uuid: v.uuid.into(),
-               pk: v.pk.into(),
-               info: serde_json::from_str::<PeerInfo>(&v.info).unwrap_or_default(),
-               ..Default::default()
-           };
-           let peer = Arc::new(RwLock::new(peer));
-           return Some(peer);
-       }
-       None
-   }
+            return Some(self.cache_loaded(v).await);
+        }
+        None
+    }
+
+    /// Caches a peer read from the database.
+    async fn cache_loaded(&self, v: database::Peer) -> LockPeer {
+        let guid = v.guid.clone();
+        let peer = Peer {
+            guid: v.guid,
+            uuid: v.uuid.into(),
+            pk: v.pk.into(),
+            info: serde_json::from_str::<PeerInfo>(&v.info).unwrap_or_default(),
+            ..Default::default()
+        };
+        self.cache_peer(v.id, Arc::new(RwLock::new(peer)), guid).await
+    }
 }
@@ -0,0 +1,178 @@
+use crate::common::get_arg;
+use crate::database::{Peer, PeerStore};
+use crate::ResultType;
+use anyhow::bail;
+use async_trait::async_trait;
+use sqlx::{
+    postgres::{PgConnectOptions, PgRow},
+    ConnectOptions, Connection, Error as SqlxError, Executor, PgConnection, Row,
+};
+use std::{ops::DerefMut, str::FromStr};
+
+type Pool = deadpool::managed::Pool<PgDbPool>;
+
+const DEFAULT_POOL_SIZE: usize = 8;
+// any constant shared by all servers migrating the same database
+const MIGRATION_LOCK: i64 = 0x6862_6273;
+
+/// Up-migrations in order, numbered like `database::MIGRATIONS` for sqlite.
+const MIGRATIONS: &[(i64, &str)] = &[(1, include_str!("../migrations/pg/0001_init.sql"))];
+const SCHEMA_VERSION: i64 = MIGRATIONS.len() as _;
+
+pub struct PgDbPool {
+    url: String,
+}
+
+#[async_trait]
+impl deadpool::managed::Manager for PgDbPool {
+    type Type = PgConnection;
+    type Error = SqlxError;
+    async fn create(&self) -> Result<PgConnection, SqlxError> {
+        let mut opt = PgConnectOptions::from_str(&self.url)?;
+        opt.log_statements(log::LevelFilter::Debug);
+        PgConnection::connect_with(&opt).await
+    }
+    async fn recycle(&self, obj: &mut PgConnection) -> deadpool::managed::RecycleResult<SqlxError> {
+        Ok(obj.ping().await?)
+    }
+}
+
+/// Peer storage in postgres, so that several rendezvous servers can share
+/// one registry. Only peers are kept here, relay audit logs stay in sqlite.
+#[derive(Clone)]
+pub struct PgDatabase {
+    pool: Pool,
+}
+
+fn peer_from_row(row: PgRow) -> ResultType<Peer> {
+    Ok(Peer {
+        guid: row.try_get("guid")?,
+        id: row.try_get("id")?,
+        uuid: row.try_get("uuid")?,
+        pk: row.try_get("pk")?,
+        user: row.try_get("user")?,
+        status: row.try_get::<Option<i16>, _>("status")?.map(|x| x as _),
+        info: row.try_get("info")?,
+    })
+}
+
+impl PgDatabase {
+    /// Connects with a pool of `db-pool-size` (default 8) connections.
+    pub async fn new(url: &str) -> ResultType<PgDatabase> {
+        let size = get_arg("db-pool-size")
+            .parse::<usize>()
+            .unwrap_or(DEFAULT_POOL_SIZE)
+            .max(1);
+        log::info!("DB pool size: {}", size);
+        let pool = Pool::new(
+            PgDbPool {
+                url: url.to_owned(),
+            },
+            size,
+        );
+        let _ = pool.get().await?;
+        let db = PgDatabase { pool };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    /// Like `Database::migrate`. Servers starting at the same time wait for
+    /// each other on an advisory lock, so each migration runs only once.
+    async fn migrate(&self) -> ResultType<()> {
+        let mut conn = self.pool.get().await?;
+        let mut tx = conn.deref_mut().begin().await?;
+        sqlx::query("select pg_advisory_xact_lock($1)")
+            .bind(MIGRATION_LOCK)
+            .execute(&mut tx)
+            .await?;
+        tx.execute(
+            "create table if not exists schema_version (
+                version bigint primary key not null,
+                applied_at timestamp not null default(current_timestamp)
+            )",
+        )
+        .await?;
+        let current: i64 =
+            sqlx::query_scalar("select coalesce(max(version), 0)::bigint from schema_version")
+                .fetch_one(&mut tx)
+                .await?;
+        if current > SCHEMA_VERSION {
+            bail!(
+                "Database schema version {} is newer than the supported {}, refusing to start",
+                current,
+                SCHEMA_VERSION
+            );
+        }
+        for (version, sql) in MIGRATIONS.iter().skip(current as _) {
+            log::info!("Migrating database to schema version {}", version);
+            tx.execute(*sql).await?;
+            sqlx::query("insert into schema_version(version) values($1)")
+                .bind(version)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PeerStore for PgDatabase {
+    async fn get_peer_by_id(&self, id: &str) -> ResultType<Option<Peer>> {
+        sqlx::query(
+            "select guid, id, uuid, pk, \"user\", status, info from peer where id = $1",
+        )
+        .bind(id)
+        .fetch_optional(self.pool.get().await?.deref_mut())
+        .await?
+        .map(peer_from_row)
+        .transpose()
+    }
+
+    async fn get_peer_by_guid(&self, guid: &[u8]) -> ResultType<Option<Peer>> {
+        sqlx::query(
+            "select guid, id, uuid, pk, \"user\", status, info from peer where guid = $1",
+        )
+        .bind(guid)
+        .fetch_optional(self.pool.get().await?.deref_mut())
+        .await?
+        .map(peer_from_row)
+        .transpose()
+    }
+
+    async fn insert_peer(
+        &self,
+        id: &str,
+        uuid: &[u8],
+        pk: &[u8],
+        info: &str,
+    ) -> ResultType<Vec<u8>> {
+        let guid = uuid::Uuid::new_v4().as_bytes().to_vec();
+        sqlx::query("insert into peer(guid, id, uuid, pk, info) values($1, $2, $3, $4, $5)")
+            .bind(&guid)
+            .bind(id)
+            .bind(uuid)
+            .bind(pk)
+            .bind(info)
+            .execute(self.pool.get().await?.deref_mut())
+            .await?;
+        Ok(guid)
+    }
+
+    async fn update_pk_by_guid(
+        &self,
+        guid: &[u8],
+        id: &str,
+        pk: &[u8],
+        info: &str,
+    ) -> ResultType<()> {
+        sqlx::query("update peer set id=$1, pk=$2, info=$3 where guid=$4")
+            .bind(id)
+            .bind(pk)
+            .bind(info)
+            .bind(guid)
+            .execute(self.pool.get().await?.deref_mut())
+            .await?;
+        Ok(())
+    }
+}
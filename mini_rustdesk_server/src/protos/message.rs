@@ -1080,6 +1080,10 @@ pub struct IdPk {
     pub id: ::std::string::String,
     // @@protoc_insertion_point(field:hbb.IdPk.pk)
     pub pk: ::bytes::Bytes,
+    // @@protoc_insertion_point(field:hbb.IdPk.direction_keys)
+    pub direction_keys: bool,
+    // @@protoc_insertion_point(field:hbb.IdPk.rekey)
+    pub rekey: bool,
     // special fields
     // @@protoc_insertion_point(special_field:hbb.IdPk.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -1097,7 +1101,7 @@ impl IdPk {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(2);
+        let mut fields = ::std::vec::Vec::with_capacity(4);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "id",
@@ -1109,6 +1113,16 @@ impl IdPk {
             |m: &IdPk| { &m.pk },
             |m: &mut IdPk| { &mut m.pk },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "direction_keys",
+            |m: &IdPk| { &m.direction_keys },
+            |m: &mut IdPk| { &mut m.direction_keys },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "rekey",
+            |m: &IdPk| { &m.rekey },
+            |m: &mut IdPk| { &mut m.rekey },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<IdPk>(
             "IdPk",
             fields,
@@ -1133,6 +1147,12 @@ impl ::protobuf::Message for IdPk {
                 18 => {
                     self.pk = is.read_tokio_bytes()?;
                 },
+                24 => {
+                    self.direction_keys = is.read_bool()?;
+                },
+                32 => {
+                    self.rekey = is.read_bool()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -1151,6 +1171,12 @@ impl ::protobuf::Message for IdPk {
         if !self.pk.is_empty() {
             my_size += ::protobuf::rt::bytes_size(2, &self.pk);
         }
+        if self.direction_keys != false {
+            my_size += 1 + 1;
+        }
+        if self.rekey != false {
+            my_size += 1 + 1;
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -1163,6 +1189,12 @@ impl ::protobuf::Message for IdPk {
         if !self.pk.is_empty() {
             os.write_bytes(2, &self.pk)?;
         }
+        if self.direction_keys != false {
+            os.write_bool(3, self.direction_keys)?;
+        }
+        if self.rekey != false {
+            os.write_bool(4, self.rekey)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -1182,6 +1214,8 @@ impl ::protobuf::Message for IdPk {
     fn clear(&mut self) {
         self.id.clear();
         self.pk.clear();
+        self.direction_keys = false;
+        self.rekey = false;
         self.special_fields.clear();
     }
 
@@ -1189,6 +1223,8 @@ impl ::protobuf::Message for IdPk {
         static instance: IdPk = IdPk {
             id: ::std::string::String::new(),
             pk: ::bytes::Bytes::new(),
+            direction_keys: false,
+            rekey: false,
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -1742,6 +1778,8 @@ pub struct LoginRequest {
     pub session_id: u64,
     // @@protoc_insertion_point(field:hbb.LoginRequest.version)
     pub version: ::std::string::String,
+    // @@protoc_insertion_point(field:hbb.LoginRequest.compress_codecs)
+    pub compress_codecs: ::std::vec::Vec<::protobuf::EnumOrUnknown<CompressCodec>>,
     // message oneof groups
     pub union: ::std::option::Option<login_request::Union>,
     // special fields
@@ -1859,7 +1897,7 @@ impl LoginRequest {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(10);
+        let mut fields = ::std::vec::Vec::with_capacity(11);
         let mut oneofs = ::std::vec::Vec::with_capacity(1);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "username",
@@ -1915,6 +1953,11 @@ impl LoginRequest {
             |m: &LoginRequest| { &m.version },
             |m: &mut LoginRequest| { &mut m.version },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_vec_simpler_accessor::<_, _>(
+            "compress_codecs",
+            |m: &LoginRequest| { &m.compress_codecs },
+            |m: &mut LoginRequest| { &mut m.compress_codecs },
+        ));
         oneofs.push(login_request::Union::generated_oneof_descriptor_data());
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<LoginRequest>(
             "LoginRequest",
@@ -1964,6 +2007,12 @@ impl ::protobuf::Message for LoginRequest {
                 90 => {
                     self.version = is.read_string()?;
                 },
+                96 => {
+                    self.compress_codecs.push(is.read_enum_or_unknown()?);
+                },
+                98 => {
+                    ::protobuf::rt::read_repeated_packed_enum_or_unknown_into(is, &mut self.compress_codecs)?
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -2001,6 +2050,7 @@ impl ::protobuf::Message for LoginRequest {
         if !self.version.is_empty() {
             my_size += ::protobuf::rt::string_size(11, &self.version);
         }
+        my_size += ::protobuf::rt::vec_packed_enum_or_unknown_size(12, &self.compress_codecs);
         if let ::std::option::Option::Some(ref v) = self.union {
             match v {
                 &login_request::Union::FileTransfer(ref v) => {
@@ -2043,6 +2093,7 @@ impl ::protobuf::Message for LoginRequest {
         if !self.version.is_empty() {
             os.write_string(11, &self.version)?;
         }
+        os.write_repeated_packed_enum_or_unknown(12, &self.compress_codecs)?;
         if let ::std::option::Option::Some(ref v) = self.union {
             match v {
                 &login_request::Union::FileTransfer(ref v) => {
@@ -2080,6 +2131,7 @@ impl ::protobuf::Message for LoginRequest {
         self.video_ack_required = false;
         self.session_id = 0;
         self.version.clear();
+        self.compress_codecs.clear();
         self.special_fields.clear();
     }
 
@@ -2093,6 +2145,7 @@ impl ::protobuf::Message for LoginRequest {
             video_ack_required: false,
             session_id: 0,
             version: ::std::string::String::new(),
+            compress_codecs: ::std::vec::Vec::new(),
             union: ::std::option::Option::None,
             special_fields: ::protobuf::SpecialFields::new(),
         };
@@ -2555,6 +2608,8 @@ pub struct PeerInfo {
     pub features: ::protobuf::MessageField<Features>,
     // @@protoc_insertion_point(field:hbb.PeerInfo.encoding)
     pub encoding: ::protobuf::MessageField<SupportedEncoding>,
+    // @@protoc_insertion_point(field:hbb.PeerInfo.compress_codec)
+    pub compress_codec: ::protobuf::EnumOrUnknown<CompressCodec>,
     // special fields
     // @@protoc_insertion_point(special_field:hbb.PeerInfo.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -2572,7 +2627,7 @@ impl PeerInfo {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(10);
+        let mut fields = ::std::vec::Vec::with_capacity(11);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "username",
@@ -2624,6 +2679,11 @@ impl PeerInfo {
             |m: &PeerInfo| { &m.encoding },
             |m: &mut PeerInfo| { &mut m.encoding },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "compress_codec",
+            |m: &PeerInfo| { &m.compress_codec },
+            |m: &mut PeerInfo| { &mut m.compress_codec },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<PeerInfo>(
             "PeerInfo",
             fields,
@@ -2672,6 +2732,9 @@ impl ::protobuf::Message for PeerInfo {
                 82 => {
                     ::protobuf::rt::read_singular_message_into_field(is, &mut self.encoding)?;
                 },
+                88 => {
+                    self.compress_codec = is.read_enum_or_unknown()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -2717,6 +2780,9 @@ impl ::protobuf::Message for PeerInfo {
             let len = v.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
         }
+        if self.compress_codec != ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD) {
+            my_size += ::protobuf::rt::int32_size(11, self.compress_codec.value());
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -2753,6 +2819,9 @@ impl ::protobuf::Message for PeerInfo {
         if let Some(v) = self.encoding.as_ref() {
             ::protobuf::rt::write_message_field_with_cached_size(10, v, os)?;
         }
+        if self.compress_codec != ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD) {
+            os.write_enum(11, ::protobuf::EnumOrUnknown::value(&self.compress_codec))?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -2780,6 +2849,7 @@ impl ::protobuf::Message for PeerInfo {
         self.conn_id = 0;
         self.features.clear();
         self.encoding.clear();
+        self.compress_codec = ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD);
         self.special_fields.clear();
     }
 
@@ -2795,6 +2865,7 @@ impl ::protobuf::Message for PeerInfo {
             conn_id: 0,
             features: ::protobuf::MessageField::none(),
             encoding: ::protobuf::MessageField::none(),
+            compress_codec: ::protobuf::EnumOrUnknown::from_i32(0),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -4184,6 +4255,8 @@ pub struct Clipboard {
     pub compress: bool,
     // @@protoc_insertion_point(field:hbb.Clipboard.content)
     pub content: ::bytes::Bytes,
+    // @@protoc_insertion_point(field:hbb.Clipboard.codec)
+    pub codec: ::protobuf::EnumOrUnknown<CompressCodec>,
     // special fields
     // @@protoc_insertion_point(special_field:hbb.Clipboard.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -4201,7 +4274,7 @@ impl Clipboard {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(2);
+        let mut fields = ::std::vec::Vec::with_capacity(3);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "compress",
@@ -4213,6 +4286,11 @@ impl Clipboard {
             |m: &Clipboard| { &m.content },
             |m: &mut Clipboard| { &mut m.content },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "codec",
+            |m: &Clipboard| { &m.codec },
+            |m: &mut Clipboard| { &mut m.codec },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<Clipboard>(
             "Clipboard",
             fields,
@@ -4237,6 +4315,9 @@ impl ::protobuf::Message for Clipboard {
                 18 => {
                     self.content = is.read_tokio_bytes()?;
                 },
+                24 => {
+                    self.codec = is.read_enum_or_unknown()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -4255,6 +4336,9 @@ impl ::protobuf::Message for Clipboard {
         if !self.content.is_empty() {
             my_size += ::protobuf::rt::bytes_size(2, &self.content);
         }
+        if self.codec != ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD) {
+            my_size += ::protobuf::rt::int32_size(3, self.codec.value());
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -4267,6 +4351,9 @@ impl ::protobuf::Message for Clipboard {
         if !self.content.is_empty() {
             os.write_bytes(2, &self.content)?;
         }
+        if self.codec != ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD) {
+            os.write_enum(3, ::protobuf::EnumOrUnknown::value(&self.codec))?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -4286,6 +4373,7 @@ impl ::protobuf::Message for Clipboard {
     fn clear(&mut self) {
         self.compress = false;
         self.content.clear();
+        self.codec = ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD);
         self.special_fields.clear();
     }
 
@@ -4293,6 +4381,7 @@ impl ::protobuf::Message for Clipboard {
         static instance: Clipboard = Clipboard {
             compress: false,
             content: ::bytes::Bytes::new(),
+            codec: ::protobuf::EnumOrUnknown::from_i32(0),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -6545,6 +6634,8 @@ pub struct FileTransferBlock {
     pub compressed: bool,
     // @@protoc_insertion_point(field:hbb.FileTransferBlock.blk_id)
     pub blk_id: u32,
+    // @@protoc_insertion_point(field:hbb.FileTransferBlock.codec)
+    pub codec: ::protobuf::EnumOrUnknown<CompressCodec>,
     // special fields
     // @@protoc_insertion_point(special_field:hbb.FileTransferBlock.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -6562,7 +6653,7 @@ impl FileTransferBlock {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(5);
+        let mut fields = ::std::vec::Vec::with_capacity(6);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "id",
@@ -6589,6 +6680,11 @@ impl FileTransferBlock {
             |m: &FileTransferBlock| { &m.blk_id },
             |m: &mut FileTransferBlock| { &mut m.blk_id },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "codec",
+            |m: &FileTransferBlock| { &m.codec },
+            |m: &mut FileTransferBlock| { &mut m.codec },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<FileTransferBlock>(
             "FileTransferBlock",
             fields,
@@ -6622,6 +6718,9 @@ impl ::protobuf::Message for FileTransferBlock {
                 40 => {
                     self.blk_id = is.read_uint32()?;
                 },
+                48 => {
+                    self.codec = is.read_enum_or_unknown()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -6649,6 +6748,9 @@ impl ::protobuf::Message for FileTransferBlock {
         if self.blk_id != 0 {
             my_size += ::protobuf::rt::uint32_size(5, self.blk_id);
         }
+        if self.codec != ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD) {
+            my_size += ::protobuf::rt::int32_size(6, self.codec.value());
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -6670,6 +6772,9 @@ impl ::protobuf::Message for FileTransferBlock {
         if self.blk_id != 0 {
             os.write_uint32(5, self.blk_id)?;
         }
+        if self.codec != ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD) {
+            os.write_enum(6, ::protobuf::EnumOrUnknown::value(&self.codec))?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -6692,6 +6797,7 @@ impl ::protobuf::Message for FileTransferBlock {
         self.data.clear();
         self.compressed = false;
         self.blk_id = 0;
+        self.codec = ::protobuf::EnumOrUnknown::new(CompressCodec::ZSTD);
         self.special_fields.clear();
     }
 
@@ -6702,6 +6808,7 @@ impl ::protobuf::Message for FileTransferBlock {
             data: ::bytes::Bytes::new(),
             compressed: false,
             blk_id: 0,
+            codec: ::protobuf::EnumOrUnknown::from_i32(0),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -11128,6 +11235,10 @@ pub struct PublicKey {
     pub asymmetric_value: ::bytes::Bytes,
     // @@protoc_insertion_point(field:hbb.PublicKey.symmetric_value)
     pub symmetric_value: ::bytes::Bytes,
+    // @@protoc_insertion_point(field:hbb.PublicKey.direction_keys)
+    pub direction_keys: bool,
+    // @@protoc_insertion_point(field:hbb.PublicKey.rekey)
+    pub rekey: bool,
     // special fields
     // @@protoc_insertion_point(special_field:hbb.PublicKey.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -11145,7 +11256,7 @@ impl PublicKey {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(2);
+        let mut fields = ::std::vec::Vec::with_capacity(4);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "asymmetric_value",
@@ -11157,6 +11268,16 @@ impl PublicKey {
             |m: &PublicKey| { &m.symmetric_value },
             |m: &mut PublicKey| { &mut m.symmetric_value },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "direction_keys",
+            |m: &PublicKey| { &m.direction_keys },
+            |m: &mut PublicKey| { &mut m.direction_keys },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "rekey",
+            |m: &PublicKey| { &m.rekey },
+            |m: &mut PublicKey| { &mut m.rekey },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<PublicKey>(
             "PublicKey",
             fields,
@@ -11181,6 +11302,12 @@ impl ::protobuf::Message for PublicKey {
                 18 => {
                     self.symmetric_value = is.read_tokio_bytes()?;
                 },
+                24 => {
+                    self.direction_keys = is.read_bool()?;
+                },
+                32 => {
+                    self.rekey = is.read_bool()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -11199,6 +11326,12 @@ impl ::protobuf::Message for PublicKey {
         if !self.symmetric_value.is_empty() {
             my_size += ::protobuf::rt::bytes_size(2, &self.symmetric_value);
         }
+        if self.direction_keys != false {
+            my_size += 1 + 1;
+        }
+        if self.rekey != false {
+            my_size += 1 + 1;
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -11211,6 +11344,12 @@ impl ::protobuf::Message for PublicKey {
         if !self.symmetric_value.is_empty() {
             os.write_bytes(2, &self.symmetric_value)?;
         }
+        if self.direction_keys != false {
+            os.write_bool(3, self.direction_keys)?;
+        }
+        if self.rekey != false {
+            os.write_bool(4, self.rekey)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -11230,6 +11369,8 @@ impl ::protobuf::Message for PublicKey {
     fn clear(&mut self) {
         self.asymmetric_value.clear();
         self.symmetric_value.clear();
+        self.direction_keys = false;
+        self.rekey = false;
         self.special_fields.clear();
     }
 
@@ -11237,6 +11378,8 @@ impl ::protobuf::Message for PublicKey {
         static instance: PublicKey = PublicKey {
             asymmetric_value: ::bytes::Bytes::new(),
             symmetric_value: ::bytes::Bytes::new(),
+            direction_keys: false,
+            rekey: false,
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -16756,6 +16899,73 @@ impl ControlKey {
     }
 }
 
+#[derive(Clone,Copy,PartialEq,Eq,Debug,Hash)]
+// @@protoc_insertion_point(enum:hbb.CompressCodec)
+pub enum CompressCodec {
+    // @@protoc_insertion_point(enum_value:hbb.CompressCodec.ZSTD)
+    ZSTD = 0,
+    // @@protoc_insertion_point(enum_value:hbb.CompressCodec.LZ4)
+    LZ4 = 1,
+    // @@protoc_insertion_point(enum_value:hbb.CompressCodec.NO_COMPRESSION)
+    NO_COMPRESSION = 2,
+}
+
+impl ::protobuf::Enum for CompressCodec {
+    const NAME: &'static str = "CompressCodec";
+
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<CompressCodec> {
+        match value {
+            0 => ::std::option::Option::Some(CompressCodec::ZSTD),
+            1 => ::std::option::Option::Some(CompressCodec::LZ4),
+            2 => ::std::option::Option::Some(CompressCodec::NO_COMPRESSION),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn from_str(str: &str) -> ::std::option::Option<CompressCodec> {
+        match str {
+            "ZSTD" => ::std::option::Option::Some(CompressCodec::ZSTD),
+            "LZ4" => ::std::option::Option::Some(CompressCodec::LZ4),
+            "NO_COMPRESSION" => ::std::option::Option::Some(CompressCodec::NO_COMPRESSION),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    const VALUES: &'static [CompressCodec] = &[
+        CompressCodec::ZSTD,
+        CompressCodec::LZ4,
+        CompressCodec::NO_COMPRESSION,
+    ];
+}
+
+impl ::protobuf::EnumFull for CompressCodec {
+    fn enum_descriptor() -> ::protobuf::reflect::EnumDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::EnumDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().enum_by_package_relative_name("CompressCodec").unwrap()).clone()
+    }
+
+    fn descriptor(&self) -> ::protobuf::reflect::EnumValueDescriptor {
+        let index = *self as usize;
+        Self::enum_descriptor().value_by_index(index)
+    }
+}
+
+impl ::std::default::Default for CompressCodec {
+    fn default() -> Self {
+        CompressCodec::ZSTD
+    }
+}
+
+impl CompressCodec {
+    fn generated_enum_descriptor_data() -> ::protobuf::reflect::GeneratedEnumDescriptorData {
+        ::protobuf::reflect::GeneratedEnumDescriptorData::new::<CompressCodec>("CompressCodec")
+    }
+}
+
 #[derive(Clone,Copy,PartialEq,Eq,Debug,Hash)]
 // @@protoc_insertion_point(enum:hbb.FileType)
 pub enum FileType {
@@ -16930,30 +17140,33 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     \x05h264s\x18\n\x20\x01(\x0b2\x17.hbb.EncodedVideoFramesH\0R\x05h264s\
     \x12/\n\x05h265s\x18\x0b\x20\x01(\x0b2\x17.hbb.EncodedVideoFramesH\0R\
     \x05h265s\x12\x1c\n\ttimestamp\x18\t\x20\x01(\x03R\ttimestampB\x07\n\x05\
-    union\"&\n\x04IdPk\x12\x0e\n\x02id\x18\x01\x20\x01(\tR\x02id\x12\x0e\n\
-    \x02pk\x18\x02\x20\x01(\x0cR\x02pk\"\xac\x01\n\x0bDisplayInfo\x12\x0c\n\
-    \x01x\x18\x01\x20\x01(\x11R\x01x\x12\x0c\n\x01y\x18\x02\x20\x01(\x11R\
-    \x01y\x12\x14\n\x05width\x18\x03\x20\x01(\x05R\x05width\x12\x16\n\x06hei\
-    ght\x18\x04\x20\x01(\x05R\x06height\x12\x12\n\x04name\x18\x05\x20\x01(\t\
-    R\x04name\x12\x16\n\x06online\x18\x06\x20\x01(\x08R\x06online\x12'\n\x0f\
-    cursor_embedded\x18\x07\x20\x01(\x08R\x0ecursorEmbedded\"5\n\x0bPortForw\
-    ard\x12\x12\n\x04host\x18\x01\x20\x01(\tR\x04host\x12\x12\n\x04port\x18\
-    \x02\x20\x01(\x05R\x04port\"A\n\x0cFileTransfer\x12\x10\n\x03dir\x18\x01\
-    \x20\x01(\tR\x03dir\x12\x1f\n\x0bshow_hidden\x18\x02\x20\x01(\x08R\nshow\
-    Hidden\"\x81\x03\n\x0cLoginRequest\x12\x1a\n\x08username\x18\x01\x20\x01\
-    (\tR\x08username\x12\x1a\n\x08password\x18\x02\x20\x01(\x0cR\x08password\
-    \x12\x13\n\x05my_id\x18\x04\x20\x01(\tR\x04myId\x12\x17\n\x07my_name\x18\
-    \x05\x20\x01(\tR\x06myName\x12*\n\x06option\x18\x06\x20\x01(\x0b2\x12.hb\
-    b.OptionMessageR\x06option\x128\n\rfile_transfer\x18\x07\x20\x01(\x0b2\
-    \x11.hbb.FileTransferH\0R\x0cfileTransfer\x125\n\x0cport_forward\x18\x08\
-    \x20\x01(\x0b2\x10.hbb.PortForwardH\0R\x0bportForward\x12,\n\x12video_ac\
-    k_required\x18\t\x20\x01(\x08R\x10videoAckRequired\x12\x1d\n\nsession_id\
-    \x18\n\x20\x01(\x04R\tsessionId\x12\x18\n\x07version\x18\x0b\x20\x01(\tR\
-    \x07versionB\x07\n\x05union\"!\n\x0bChatMessage\x12\x12\n\x04text\x18\
+    union\"c\n\x04IdPk\x12\x0e\n\x02id\x18\x01\x20\x01(\tR\x02id\x12\x0e\n\
+    \x02pk\x18\x02\x20\x01(\x0cR\x02pk\x12%\n\x0edirection_keys\x18\x03\x20\
+    \x01(\x08R\rdirectionKeys\x12\x14\n\x05rekey\x18\x04\x20\x01(\x08R\x05re\
+    key\"\xac\x01\n\x0bDisplayInfo\x12\x0c\n\x01x\x18\x01\x20\x01(\x11R\x01x\
+    \x12\x0c\n\x01y\x18\x02\x20\x01(\x11R\x01y\x12\x14\n\x05width\x18\x03\
+    \x20\x01(\x05R\x05width\x12\x16\n\x06height\x18\x04\x20\x01(\x05R\x06hei\
+    ght\x12\x12\n\x04name\x18\x05\x20\x01(\tR\x04name\x12\x16\n\x06online\
+    \x18\x06\x20\x01(\x08R\x06online\x12'\n\x0fcursor_embedded\x18\x07\x20\
+    \x01(\x08R\x0ecursorEmbedded\"5\n\x0bPortForward\x12\x12\n\x04host\x18\
+    \x01\x20\x01(\tR\x04host\x12\x12\n\x04port\x18\x02\x20\x01(\x05R\x04port\
+    \"A\n\x0cFileTransfer\x12\x10\n\x03dir\x18\x01\x20\x01(\tR\x03dir\x12\
+    \x1f\n\x0bshow_hidden\x18\x02\x20\x01(\x08R\nshowHidden\"\xbe\x03\n\x0cL\
+    oginRequest\x12\x1a\n\x08username\x18\x01\x20\x01(\tR\x08username\x12\
+    \x1a\n\x08password\x18\x02\x20\x01(\x0cR\x08password\x12\x13\n\x05my_id\
+    \x18\x04\x20\x01(\tR\x04myId\x12\x17\n\x07my_name\x18\x05\x20\x01(\tR\
+    \x06myName\x12*\n\x06option\x18\x06\x20\x01(\x0b2\x12.hbb.OptionMessageR\
+    \x06option\x128\n\rfile_transfer\x18\x07\x20\x01(\x0b2\x11.hbb.FileTrans\
+    ferH\0R\x0cfileTransfer\x125\n\x0cport_forward\x18\x08\x20\x01(\x0b2\x10\
+    .hbb.PortForwardH\0R\x0bportForward\x12,\n\x12video_ack_required\x18\t\
+    \x20\x01(\x08R\x10videoAckRequired\x12\x1d\n\nsession_id\x18\n\x20\x01(\
+    \x04R\tsessionId\x12\x18\n\x07version\x18\x0b\x20\x01(\tR\x07version\x12\
+    ;\n\x0fcompress_codecs\x18\x0c\x20\x03(\x0e2\x12.hbb.CompressCodecR\x0ec\
+    ompressCodecsB\x07\n\x05union\"!\n\x0bChatMessage\x12\x12\n\x04text\x18\
     \x01\x20\x01(\tR\x04text\"-\n\x08Features\x12!\n\x0cprivacy_mode\x18\x01\
     \x20\x01(\x08R\x0bprivacyMode\";\n\x11SupportedEncoding\x12\x12\n\x04h26\
     4\x18\x01\x20\x01(\x08R\x04h264\x12\x12\n\x04h265\x18\x02\x20\x01(\x08R\
-    \x04h265\"\xe8\x02\n\x08PeerInfo\x12\x1a\n\x08username\x18\x01\x20\x01(\
+    \x04h265\"\xa3\x03\n\x08PeerInfo\x12\x1a\n\x08username\x18\x01\x20\x01(\
     \tR\x08username\x12\x1a\n\x08hostname\x18\x02\x20\x01(\tR\x08hostname\
     \x12\x1a\n\x08platform\x18\x03\x20\x01(\tR\x08platform\x12,\n\x08display\
     s\x18\x04\x20\x03(\x0b2\x10.hbb.DisplayInfoR\x08displays\x12'\n\x0fcurre\
@@ -16962,158 +17175,163 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     \x01(\tR\x07version\x12\x17\n\x07conn_id\x18\x08\x20\x01(\x05R\x06connId\
     \x12)\n\x08features\x18\t\x20\x01(\x0b2\r.hbb.FeaturesR\x08features\x122\
     \n\x08encoding\x18\n\x20\x01(\x0b2\x16.hbb.SupportedEncodingR\x08encodin\
-    g\"^\n\rLoginResponse\x12\x16\n\x05error\x18\x01\x20\x01(\tH\0R\x05error\
-    \x12,\n\tpeer_info\x18\x02\x20\x01(\x0b2\r.hbb.PeerInfoH\0R\x08peerInfoB\
-    \x07\n\x05union\"k\n\nMouseEvent\x12\x12\n\x04mask\x18\x01\x20\x01(\x05R\
-    \x04mask\x12\x0c\n\x01x\x18\x02\x20\x01(\x11R\x01x\x12\x0c\n\x01y\x18\
-    \x03\x20\x01(\x11R\x01y\x12-\n\tmodifiers\x18\x04\x20\x03(\x0e2\x0f.hbb.\
-    ControlKeyR\tmodifiers\"\x8b\x02\n\x08KeyEvent\x12\x12\n\x04down\x18\x01\
-    \x20\x01(\x08R\x04down\x12\x14\n\x05press\x18\x02\x20\x01(\x08R\x05press\
-    \x122\n\x0bcontrol_key\x18\x03\x20\x01(\x0e2\x0f.hbb.ControlKeyH\0R\ncon\
-    trolKey\x12\x12\n\x03chr\x18\x04\x20\x01(\rH\0R\x03chr\x12\x1a\n\x07unic\
-    ode\x18\x05\x20\x01(\rH\0R\x07unicode\x12\x12\n\x03seq\x18\x06\x20\x01(\
-    \tH\0R\x03seq\x12-\n\tmodifiers\x18\x08\x20\x03(\x0e2\x0f.hbb.ControlKey\
-    R\tmodifiers\x12%\n\x04mode\x18\t\x20\x01(\x0e2\x11.hbb.KeyboardModeR\
-    \x04modeB\x07\n\x05union\"\x8a\x01\n\nCursorData\x12\x0e\n\x02id\x18\x01\
-    \x20\x01(\x04R\x02id\x12\x12\n\x04hotx\x18\x02\x20\x01(\x11R\x04hotx\x12\
-    \x12\n\x04hoty\x18\x03\x20\x01(\x11R\x04hoty\x12\x14\n\x05width\x18\x04\
-    \x20\x01(\x05R\x05width\x12\x16\n\x06height\x18\x05\x20\x01(\x05R\x06hei\
-    ght\x12\x16\n\x06colors\x18\x06\x20\x01(\x0cR\x06colors\",\n\x0eCursorPo\
-    sition\x12\x0c\n\x01x\x18\x01\x20\x01(\x11R\x01x\x12\x0c\n\x01y\x18\x02\
-    \x20\x01(\x11R\x01y\"8\n\x04Hash\x12\x12\n\x04salt\x18\x01\x20\x01(\tR\
-    \x04salt\x12\x1c\n\tchallenge\x18\x02\x20\x01(\tR\tchallenge\"A\n\tClipb\
-    oard\x12\x1a\n\x08compress\x18\x01\x20\x01(\x08R\x08compress\x12\x18\n\
-    \x07content\x18\x02\x20\x01(\x0cR\x07content\"\xa3\x01\n\tFileEntry\x12,\
-    \n\nentry_type\x18\x01\x20\x01(\x0e2\r.hbb.FileTypeR\tentryType\x12\x12\
-    \n\x04name\x18\x02\x20\x01(\tR\x04name\x12\x1b\n\tis_hidden\x18\x03\x20\
-    \x01(\x08R\x08isHidden\x12\x12\n\x04size\x18\x04\x20\x01(\x04R\x04size\
-    \x12#\n\rmodified_time\x18\x05\x20\x01(\x04R\x0cmodifiedTime\"]\n\rFileD\
-    irectory\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\
-    \x18\x02\x20\x01(\tR\x04path\x12(\n\x07entries\x18\x03\x20\x03(\x0b2\x0e\
-    .hbb.FileEntryR\x07entries\"D\n\x07ReadDir\x12\x12\n\x04path\x18\x01\x20\
-    \x01(\tR\x04path\x12%\n\x0einclude_hidden\x18\x02\x20\x01(\x08R\rinclude\
-    Hidden\"Y\n\x0cReadAllFiles\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\
-    \x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\x12%\n\x0einclude_hidden\
-    \x18\x03\x20\x01(\x08R\rincludeHidden\"\xfb\x03\n\nFileAction\x12)\n\x08\
-    read_dir\x18\x01\x20\x01(\x0b2\x0c.hbb.ReadDirH\0R\x07readDir\x122\n\x04\
-    send\x18\x02\x20\x01(\x0b2\x1c.hbb.FileTransferSendRequestH\0R\x04send\
-    \x12;\n\x07receive\x18\x03\x20\x01(\x0b2\x1f.hbb.FileTransferReceiveRequ\
-    estH\0R\x07receive\x12,\n\x06create\x18\x04\x20\x01(\x0b2\x12.hbb.FileDi\
-    rCreateH\0R\x06create\x123\n\nremove_dir\x18\x05\x20\x01(\x0b2\x12.hbb.F\
-    ileRemoveDirH\0R\tremoveDir\x126\n\x0bremove_file\x18\x06\x20\x01(\x0b2\
-    \x13.hbb.FileRemoveFileH\0R\nremoveFile\x120\n\tall_files\x18\x07\x20\
-    \x01(\x0b2\x11.hbb.ReadAllFilesH\0R\x08allFiles\x121\n\x06cancel\x18\x08\
-    \x20\x01(\x0b2\x17.hbb.FileTransferCancelH\0R\x06cancel\x12H\n\x0csend_c\
-    onfirm\x18\t\x20\x01(\x0b2#.hbb.FileTransferSendConfirmRequestH\0R\x0bse\
-    ndConfirmB\x07\n\x05union\"$\n\x12FileTransferCancel\x12\x0e\n\x02id\x18\
-    \x01\x20\x01(\x05R\x02id\"\xff\x01\n\x0cFileResponse\x12&\n\x03dir\x18\
-    \x01\x20\x01(\x0b2\x12.hbb.FileDirectoryH\0R\x03dir\x12.\n\x05block\x18\
-    \x02\x20\x01(\x0b2\x16.hbb.FileTransferBlockH\0R\x05block\x12.\n\x05erro\
-    r\x18\x03\x20\x01(\x0b2\x16.hbb.FileTransferErrorH\0R\x05error\x12+\n\
-    \x04done\x18\x04\x20\x01(\x0b2\x15.hbb.FileTransferDoneH\0R\x04done\x121\
-    \n\x06digest\x18\x05\x20\x01(\x0b2\x17.hbb.FileTransferDigestH\0R\x06dig\
-    estB\x07\n\x05union\"\x9e\x01\n\x12FileTransferDigest\x12\x0e\n\x02id\
-    \x18\x01\x20\x01(\x05R\x02id\x12\x19\n\x08file_num\x18\x02\x20\x01(\x11R\
-    \x07fileNum\x12#\n\rlast_modified\x18\x03\x20\x01(\x04R\x0clastModified\
-    \x12\x1b\n\tfile_size\x18\x04\x20\x01(\x04R\x08fileSize\x12\x1b\n\tis_up\
-    load\x18\x05\x20\x01(\x08R\x08isUpload\"\x89\x01\n\x11FileTransferBlock\
-    \x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x19\n\x08file_num\x18\
-    \x02\x20\x01(\x11R\x07fileNum\x12\x12\n\x04data\x18\x03\x20\x01(\x0cR\
-    \x04data\x12\x1e\n\ncompressed\x18\x04\x20\x01(\x08R\ncompressed\x12\x15\
-    \n\x06blk_id\x18\x05\x20\x01(\rR\x05blkId\"T\n\x11FileTransferError\x12\
-    \x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x14\n\x05error\x18\x02\x20\
-    \x01(\tR\x05error\x12\x19\n\x08file_num\x18\x03\x20\x01(\x11R\x07fileNum\
-    \"\x7f\n\x17FileTransferSendRequest\x12\x0e\n\x02id\x18\x01\x20\x01(\x05\
-    R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\x12%\n\x0einclude_\
-    hidden\x18\x03\x20\x01(\x08R\rincludeHidden\x12\x19\n\x08file_num\x18\
-    \x04\x20\x01(\x05R\x07fileNum\"\x8b\x01\n\x1eFileTransferSendConfirmRequ\
-    est\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x19\n\x08file_num\
-    \x18\x02\x20\x01(\x11R\x07fileNum\x12\x14\n\x04skip\x18\x03\x20\x01(\x08\
-    H\0R\x04skip\x12\x1f\n\noffset_blk\x18\x04\x20\x01(\rH\0R\toffsetBlkB\
-    \x07\n\x05union\"=\n\x10FileTransferDone\x12\x0e\n\x02id\x18\x01\x20\x01\
-    (\x05R\x02id\x12\x19\n\x08file_num\x18\x02\x20\x01(\x11R\x07fileNum\"\
-    \x81\x01\n\x1aFileTransferReceiveRequest\x12\x0e\n\x02id\x18\x01\x20\x01\
-    (\x05R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\x12$\n\x05fil\
-    es\x18\x03\x20\x03(\x0b2\x0e.hbb.FileEntryR\x05files\x12\x19\n\x08file_n\
-    um\x18\x04\x20\x01(\x05R\x07fileNum\"Q\n\rFileRemoveDir\x12\x0e\n\x02id\
-    \x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04pa\
-    th\x12\x1c\n\trecursive\x18\x03\x20\x01(\x08R\trecursive\"O\n\x0eFileRem\
-    oveFile\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\
-    \x18\x02\x20\x01(\tR\x04path\x12\x19\n\x08file_num\x18\x03\x20\x01(\x11R\
-    \x07fileNum\"3\n\rFileDirCreate\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\
-    \x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\"\x15\n\x13CliprdrM\
-    onitorReady\"7\n\rCliprdrFormat\x12\x0e\n\x02id\x18\x02\x20\x01(\x05R\
-    \x02id\x12\x16\n\x06format\x18\x03\x20\x01(\tR\x06format\"G\n\x17Cliprdr\
-    ServerFormatList\x12,\n\x07formats\x18\x02\x20\x03(\x0b2\x12.hbb.Cliprdr\
-    FormatR\x07formats\">\n\x1fCliprdrServerFormatListResponse\x12\x1b\n\tms\
-    g_flags\x18\x02\x20\x01(\x05R\x08msgFlags\"P\n\x1eCliprdrServerFormatDat\
-    aRequest\x12.\n\x13requested_format_id\x18\x02\x20\x01(\x05R\x11requeste\
-    dFormatId\"_\n\x1fCliprdrServerFormatDataResponse\x12\x1b\n\tmsg_flags\
-    \x18\x02\x20\x01(\x05R\x08msgFlags\x12\x1f\n\x0bformat_data\x18\x03\x20\
-    \x01(\x0cR\nformatData\"\xb1\x02\n\x1aCliprdrFileContentsRequest\x12\x1b\
-    \n\tstream_id\x18\x02\x20\x01(\x05R\x08streamId\x12\x1d\n\nlist_index\
-    \x18\x03\x20\x01(\x05R\tlistIndex\x12\x19\n\x08dw_flags\x18\x04\x20\x01(\
-    \x05R\x07dwFlags\x12$\n\x0en_position_low\x18\x05\x20\x01(\x05R\x0cnPosi\
-    tionLow\x12&\n\x0fn_position_high\x18\x06\x20\x01(\x05R\rnPositionHigh\
-    \x12!\n\x0ccb_requested\x18\x07\x20\x01(\x05R\x0bcbRequested\x12)\n\x11h\
-    ave_clip_data_id\x18\x08\x20\x01(\x08R\x0ehaveClipDataId\x12\x20\n\x0ccl\
-    ip_data_id\x18\t\x20\x01(\x05R\nclipDataId\"~\n\x1bCliprdrFileContentsRe\
-    sponse\x12\x1b\n\tmsg_flags\x18\x03\x20\x01(\x05R\x08msgFlags\x12\x1b\n\
-    \tstream_id\x18\x04\x20\x01(\x05R\x08streamId\x12%\n\x0erequested_data\
-    \x18\x05\x20\x01(\x0cR\rrequestedData\"\xc1\x04\n\x07Cliprdr\x120\n\x05r\
-    eady\x18\x01\x20\x01(\x0b2\x18.hbb.CliprdrMonitorReadyH\0R\x05ready\x12?\
-    \n\x0bformat_list\x18\x02\x20\x01(\x0b2\x1c.hbb.CliprdrServerFormatListH\
-    \0R\nformatList\x12X\n\x14format_list_response\x18\x03\x20\x01(\x0b2$.hb\
-    b.CliprdrServerFormatListResponseH\0R\x12formatListResponse\x12U\n\x13fo\
-    rmat_data_request\x18\x04\x20\x01(\x0b2#.hbb.CliprdrServerFormatDataRequ\
-    estH\0R\x11formatDataRequest\x12X\n\x14format_data_response\x18\x05\x20\
-    \x01(\x0b2$.hbb.CliprdrServerFormatDataResponseH\0R\x12formatDataRespons\
-    e\x12U\n\x15file_contents_request\x18\x06\x20\x01(\x0b2\x1f.hbb.CliprdrF\
-    ileContentsRequestH\0R\x13fileContentsRequest\x12X\n\x16file_contents_re\
-    sponse\x18\x07\x20\x01(\x0b2\x20.hbb.CliprdrFileContentsResponseH\0R\x14\
-    fileContentsResponseB\x07\n\x05union\"\x9c\x01\n\rSwitchDisplay\x12\x18\
-    \n\x07display\x18\x01\x20\x01(\x05R\x07display\x12\x0c\n\x01x\x18\x02\
-    \x20\x01(\x11R\x01x\x12\x0c\n\x01y\x18\x03\x20\x01(\x11R\x01y\x12\x14\n\
+    g\x129\n\x0ecompress_codec\x18\x0b\x20\x01(\x0e2\x12.hbb.CompressCodecR\
+    \rcompressCodec\"^\n\rLoginResponse\x12\x16\n\x05error\x18\x01\x20\x01(\
+    \tH\0R\x05error\x12,\n\tpeer_info\x18\x02\x20\x01(\x0b2\r.hbb.PeerInfoH\
+    \0R\x08peerInfoB\x07\n\x05union\"k\n\nMouseEvent\x12\x12\n\x04mask\x18\
+    \x01\x20\x01(\x05R\x04mask\x12\x0c\n\x01x\x18\x02\x20\x01(\x11R\x01x\x12\
+    \x0c\n\x01y\x18\x03\x20\x01(\x11R\x01y\x12-\n\tmodifiers\x18\x04\x20\x03\
+    (\x0e2\x0f.hbb.ControlKeyR\tmodifiers\"\x8b\x02\n\x08KeyEvent\x12\x12\n\
+    \x04down\x18\x01\x20\x01(\x08R\x04down\x12\x14\n\x05press\x18\x02\x20\
+    \x01(\x08R\x05press\x122\n\x0bcontrol_key\x18\x03\x20\x01(\x0e2\x0f.hbb.\
+    ControlKeyH\0R\ncontrolKey\x12\x12\n\x03chr\x18\x04\x20\x01(\rH\0R\x03ch\
+    r\x12\x1a\n\x07unicode\x18\x05\x20\x01(\rH\0R\x07unicode\x12\x12\n\x03se\
+    q\x18\x06\x20\x01(\tH\0R\x03seq\x12-\n\tmodifiers\x18\x08\x20\x03(\x0e2\
+    \x0f.hbb.ControlKeyR\tmodifiers\x12%\n\x04mode\x18\t\x20\x01(\x0e2\x11.h\
+    bb.KeyboardModeR\x04modeB\x07\n\x05union\"\x8a\x01\n\nCursorData\x12\x0e\
+    \n\x02id\x18\x01\x20\x01(\x04R\x02id\x12\x12\n\x04hotx\x18\x02\x20\x01(\
+    \x11R\x04hotx\x12\x12\n\x04hoty\x18\x03\x20\x01(\x11R\x04hoty\x12\x14\n\
     \x05width\x18\x04\x20\x01(\x05R\x05width\x12\x16\n\x06height\x18\x05\x20\
-    \x01(\x05R\x06height\x12'\n\x0fcursor_embedded\x18\x06\x20\x01(\x08R\x0e\
-    cursorEmbedded\"\xc6\x01\n\x0ePermissionInfo\x12>\n\npermission\x18\x01\
-    \x20\x01(\x0e2\x1e.hbb.PermissionInfo.PermissionR\npermission\x12\x18\n\
-    \x07enabled\x18\x02\x20\x01(\x08R\x07enabled\"Z\n\nPermission\x12\x0c\n\
-    \x08Keyboard\x10\0\x12\r\n\tClipboard\x10\x02\x12\t\n\x05Audio\x10\x03\
-    \x12\x08\n\x04File\x10\x04\x12\x0b\n\x07Restart\x10\x05\x12\r\n\tRecordi\
-    ng\x10\x06\"\xdc\x01\n\x0fVideoCodecState\x12\x1b\n\tscore_vpx\x18\x01\
-    \x20\x01(\x05R\x08scoreVpx\x12\x1d\n\nscore_h264\x18\x02\x20\x01(\x05R\t\
-    scoreH264\x12\x1d\n\nscore_h265\x18\x03\x20\x01(\x05R\tscoreH265\x128\n\
-    \x06prefer\x18\x04\x20\x01(\x0e2\x20.hbb.VideoCodecState.PreferCodecR\
-    \x06prefer\"4\n\x0bPreferCodec\x12\x08\n\x04Auto\x10\0\x12\x07\n\x03VPX\
-    \x10\x01\x12\x08\n\x04H264\x10\x02\x12\x08\n\x04H265\x10\x03\"\x89\x06\n\
-    \rOptionMessage\x126\n\rimage_quality\x18\x01\x20\x01(\x0e2\x11.hbb.Imag\
-    eQualityR\x0cimageQuality\x12R\n\x16lock_after_session_end\x18\x02\x20\
-    \x01(\x0e2\x1d.hbb.OptionMessage.BoolOptionR\x13lockAfterSessionEnd\x12K\
-    \n\x12show_remote_cursor\x18\x03\x20\x01(\x0e2\x1d.hbb.OptionMessage.Boo\
-    lOptionR\x10showRemoteCursor\x12@\n\x0cprivacy_mode\x18\x04\x20\x01(\x0e\
-    2\x1d.hbb.OptionMessage.BoolOptionR\x0bprivacyMode\x12>\n\x0bblock_input\
-    \x18\x05\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolOptionR\nblockInput\x12\
-    0\n\x14custom_image_quality\x18\x06\x20\x01(\x05R\x12customImageQuality\
-    \x12B\n\rdisable_audio\x18\x07\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolO\
-    ptionR\x0cdisableAudio\x12J\n\x11disable_clipboard\x18\x08\x20\x01(\x0e2\
-    \x1d.hbb.OptionMessage.BoolOptionR\x10disableClipboard\x12O\n\x14enable_\
-    file_transfer\x18\t\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolOptionR\x12e\
-    nableFileTransfer\x12@\n\x11video_codec_state\x18\n\x20\x01(\x0b2\x14.hb\
-    b.VideoCodecStateR\x0fvideoCodecState\x12\x1d\n\ncustom_fps\x18\x0b\x20\
-    \x01(\x05R\tcustomFps\")\n\nBoolOption\x12\n\n\x06NotSet\x10\0\x12\x06\n\
-    \x02No\x10\x01\x12\x07\n\x03Yes\x10\x02\"\x86\x01\n\tTestDelay\x12\x12\n\
-    \x04time\x18\x01\x20\x01(\x03R\x04time\x12\x1f\n\x0bfrom_client\x18\x02\
-    \x20\x01(\x08R\nfromClient\x12\x1d\n\nlast_delay\x18\x03\x20\x01(\rR\tla\
-    stDelay\x12%\n\x0etarget_bitrate\x18\x04\x20\x01(\rR\rtargetBitrate\"_\n\
-    \tPublicKey\x12)\n\x10asymmetric_value\x18\x01\x20\x01(\x0cR\x0fasymmetr\
-    icValue\x12'\n\x0fsymmetric_value\x18\x02\x20\x01(\x0cR\x0esymmetricValu\
-    e\"\x1a\n\x08SignedId\x12\x0e\n\x02id\x18\x01\x20\x01(\x0cR\x02id\"J\n\
-    \x0bAudioFormat\x12\x1f\n\x0bsample_rate\x18\x01\x20\x01(\rR\nsampleRate\
-    \x12\x1a\n\x08channels\x18\x02\x20\x01(\rR\x08channels\">\n\nAudioFrame\
-    \x12\x12\n\x04data\x18\x01\x20\x01(\x0cR\x04data\x12\x1c\n\ttimestamp\
-    \x18\x02\x20\x01(\x03R\ttimestamp\"d\n\nMessageBox\x12\x18\n\x07msgtype\
-    \x18\x01\x20\x01(\tR\x07msgtype\x12\x14\n\x05title\x18\x02\x20\x01(\tR\
+    \x01(\x05R\x06height\x12\x16\n\x06colors\x18\x06\x20\x01(\x0cR\x06colors\
+    \",\n\x0eCursorPosition\x12\x0c\n\x01x\x18\x01\x20\x01(\x11R\x01x\x12\
+    \x0c\n\x01y\x18\x02\x20\x01(\x11R\x01y\"8\n\x04Hash\x12\x12\n\x04salt\
+    \x18\x01\x20\x01(\tR\x04salt\x12\x1c\n\tchallenge\x18\x02\x20\x01(\tR\tc\
+    hallenge\"k\n\tClipboard\x12\x1a\n\x08compress\x18\x01\x20\x01(\x08R\x08\
+    compress\x12\x18\n\x07content\x18\x02\x20\x01(\x0cR\x07content\x12(\n\
+    \x05codec\x18\x03\x20\x01(\x0e2\x12.hbb.CompressCodecR\x05codec\"\xa3\
+    \x01\n\tFileEntry\x12,\n\nentry_type\x18\x01\x20\x01(\x0e2\r.hbb.FileTyp\
+    eR\tentryType\x12\x12\n\x04name\x18\x02\x20\x01(\tR\x04name\x12\x1b\n\ti\
+    s_hidden\x18\x03\x20\x01(\x08R\x08isHidden\x12\x12\n\x04size\x18\x04\x20\
+    \x01(\x04R\x04size\x12#\n\rmodified_time\x18\x05\x20\x01(\x04R\x0cmodifi\
+    edTime\"]\n\rFileDirectory\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\
+    \x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\x12(\n\x07entries\x18\x03\
+    \x20\x03(\x0b2\x0e.hbb.FileEntryR\x07entries\"D\n\x07ReadDir\x12\x12\n\
+    \x04path\x18\x01\x20\x01(\tR\x04path\x12%\n\x0einclude_hidden\x18\x02\
+    \x20\x01(\x08R\rincludeHidden\"Y\n\x0cReadAllFiles\x12\x0e\n\x02id\x18\
+    \x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\
+    \x12%\n\x0einclude_hidden\x18\x03\x20\x01(\x08R\rincludeHidden\"\xfb\x03\
+    \n\nFileAction\x12)\n\x08read_dir\x18\x01\x20\x01(\x0b2\x0c.hbb.ReadDirH\
+    \0R\x07readDir\x122\n\x04send\x18\x02\x20\x01(\x0b2\x1c.hbb.FileTransfer\
+    SendRequestH\0R\x04send\x12;\n\x07receive\x18\x03\x20\x01(\x0b2\x1f.hbb.\
+    FileTransferReceiveRequestH\0R\x07receive\x12,\n\x06create\x18\x04\x20\
+    \x01(\x0b2\x12.hbb.FileDirCreateH\0R\x06create\x123\n\nremove_dir\x18\
+    \x05\x20\x01(\x0b2\x12.hbb.FileRemoveDirH\0R\tremoveDir\x126\n\x0bremove\
+    _file\x18\x06\x20\x01(\x0b2\x13.hbb.FileRemoveFileH\0R\nremoveFile\x120\
+    \n\tall_files\x18\x07\x20\x01(\x0b2\x11.hbb.ReadAllFilesH\0R\x08allFiles\
+    \x121\n\x06cancel\x18\x08\x20\x01(\x0b2\x17.hbb.FileTransferCancelH\0R\
+    \x06cancel\x12H\n\x0csend_confirm\x18\t\x20\x01(\x0b2#.hbb.FileTransferS\
+    endConfirmRequestH\0R\x0bsendConfirmB\x07\n\x05union\"$\n\x12FileTransfe\
+    rCancel\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\"\xff\x01\n\x0cFileR\
+    esponse\x12&\n\x03dir\x18\x01\x20\x01(\x0b2\x12.hbb.FileDirectoryH\0R\
+    \x03dir\x12.\n\x05block\x18\x02\x20\x01(\x0b2\x16.hbb.FileTransferBlockH\
+    \0R\x05block\x12.\n\x05error\x18\x03\x20\x01(\x0b2\x16.hbb.FileTransferE\
+    rrorH\0R\x05error\x12+\n\x04done\x18\x04\x20\x01(\x0b2\x15.hbb.FileTrans\
+    ferDoneH\0R\x04done\x121\n\x06digest\x18\x05\x20\x01(\x0b2\x17.hbb.FileT\
+    ransferDigestH\0R\x06digestB\x07\n\x05union\"\x9e\x01\n\x12FileTransferD\
+    igest\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x19\n\x08file_num\
+    \x18\x02\x20\x01(\x11R\x07fileNum\x12#\n\rlast_modified\x18\x03\x20\x01(\
+    \x04R\x0clastModified\x12\x1b\n\tfile_size\x18\x04\x20\x01(\x04R\x08file\
+    Size\x12\x1b\n\tis_upload\x18\x05\x20\x01(\x08R\x08isUpload\"\xb3\x01\n\
+    \x11FileTransferBlock\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\
+    \x19\n\x08file_num\x18\x02\x20\x01(\x11R\x07fileNum\x12\x12\n\x04data\
+    \x18\x03\x20\x01(\x0cR\x04data\x12\x1e\n\ncompressed\x18\x04\x20\x01(\
+    \x08R\ncompressed\x12\x15\n\x06blk_id\x18\x05\x20\x01(\rR\x05blkId\x12(\
+    \n\x05codec\x18\x06\x20\x01(\x0e2\x12.hbb.CompressCodecR\x05codec\"T\n\
+    \x11FileTransferError\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\
+    \x14\n\x05error\x18\x02\x20\x01(\tR\x05error\x12\x19\n\x08file_num\x18\
+    \x03\x20\x01(\x11R\x07fileNum\"\x7f\n\x17FileTransferSendRequest\x12\x0e\
+    \n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\
+    \tR\x04path\x12%\n\x0einclude_hidden\x18\x03\x20\x01(\x08R\rincludeHidde\
+    n\x12\x19\n\x08file_num\x18\x04\x20\x01(\x05R\x07fileNum\"\x8b\x01\n\x1e\
+    FileTransferSendConfirmRequest\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02\
+    id\x12\x19\n\x08file_num\x18\x02\x20\x01(\x11R\x07fileNum\x12\x14\n\x04s\
+    kip\x18\x03\x20\x01(\x08H\0R\x04skip\x12\x1f\n\noffset_blk\x18\x04\x20\
+    \x01(\rH\0R\toffsetBlkB\x07\n\x05union\"=\n\x10FileTransferDone\x12\x0e\
+    \n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x19\n\x08file_num\x18\x02\x20\
+    \x01(\x11R\x07fileNum\"\x81\x01\n\x1aFileTransferReceiveRequest\x12\x0e\
+    \n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\
+    \tR\x04path\x12$\n\x05files\x18\x03\x20\x03(\x0b2\x0e.hbb.FileEntryR\x05\
+    files\x12\x19\n\x08file_num\x18\x04\x20\x01(\x05R\x07fileNum\"Q\n\rFileR\
+    emoveDir\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\
+    \x18\x02\x20\x01(\tR\x04path\x12\x1c\n\trecursive\x18\x03\x20\x01(\x08R\
+    \trecursive\"O\n\x0eFileRemoveFile\x12\x0e\n\x02id\x18\x01\x20\x01(\x05R\
+    \x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04path\x12\x19\n\x08file_n\
+    um\x18\x03\x20\x01(\x11R\x07fileNum\"3\n\rFileDirCreate\x12\x0e\n\x02id\
+    \x18\x01\x20\x01(\x05R\x02id\x12\x12\n\x04path\x18\x02\x20\x01(\tR\x04pa\
+    th\"\x15\n\x13CliprdrMonitorReady\"7\n\rCliprdrFormat\x12\x0e\n\x02id\
+    \x18\x02\x20\x01(\x05R\x02id\x12\x16\n\x06format\x18\x03\x20\x01(\tR\x06\
+    format\"G\n\x17CliprdrServerFormatList\x12,\n\x07formats\x18\x02\x20\x03\
+    (\x0b2\x12.hbb.CliprdrFormatR\x07formats\">\n\x1fCliprdrServerFormatList\
+    Response\x12\x1b\n\tmsg_flags\x18\x02\x20\x01(\x05R\x08msgFlags\"P\n\x1e\
+    CliprdrServerFormatDataRequest\x12.\n\x13requested_format_id\x18\x02\x20\
+    \x01(\x05R\x11requestedFormatId\"_\n\x1fCliprdrServerFormatDataResponse\
+    \x12\x1b\n\tmsg_flags\x18\x02\x20\x01(\x05R\x08msgFlags\x12\x1f\n\x0bfor\
+    mat_data\x18\x03\x20\x01(\x0cR\nformatData\"\xb1\x02\n\x1aCliprdrFileCon\
+    tentsRequest\x12\x1b\n\tstream_id\x18\x02\x20\x01(\x05R\x08streamId\x12\
+    \x1d\n\nlist_index\x18\x03\x20\x01(\x05R\tlistIndex\x12\x19\n\x08dw_flag\
+    s\x18\x04\x20\x01(\x05R\x07dwFlags\x12$\n\x0en_position_low\x18\x05\x20\
+    \x01(\x05R\x0cnPositionLow\x12&\n\x0fn_position_high\x18\x06\x20\x01(\
+    \x05R\rnPositionHigh\x12!\n\x0ccb_requested\x18\x07\x20\x01(\x05R\x0bcbR\
+    equested\x12)\n\x11have_clip_data_id\x18\x08\x20\x01(\x08R\x0ehaveClipDa\
+    taId\x12\x20\n\x0cclip_data_id\x18\t\x20\x01(\x05R\nclipDataId\"~\n\x1bC\
+    liprdrFileContentsResponse\x12\x1b\n\tmsg_flags\x18\x03\x20\x01(\x05R\
+    \x08msgFlags\x12\x1b\n\tstream_id\x18\x04\x20\x01(\x05R\x08streamId\x12%\
+    \n\x0erequested_data\x18\x05\x20\x01(\x0cR\rrequestedData\"\xc1\x04\n\
+    \x07Cliprdr\x120\n\x05ready\x18\x01\x20\x01(\x0b2\x18.hbb.CliprdrMonitor\
+    ReadyH\0R\x05ready\x12?\n\x0bformat_list\x18\x02\x20\x01(\x0b2\x1c.hbb.C\
+    liprdrServerFormatListH\0R\nformatList\x12X\n\x14format_list_response\
+    \x18\x03\x20\x01(\x0b2$.hbb.CliprdrServerFormatListResponseH\0R\x12forma\
+    tListResponse\x12U\n\x13format_data_request\x18\x04\x20\x01(\x0b2#.hbb.C\
+    liprdrServerFormatDataRequestH\0R\x11formatDataRequest\x12X\n\x14format_\
+    data_response\x18\x05\x20\x01(\x0b2$.hbb.CliprdrServerFormatDataResponse\
+    H\0R\x12formatDataResponse\x12U\n\x15file_contents_request\x18\x06\x20\
+    \x01(\x0b2\x1f.hbb.CliprdrFileContentsRequestH\0R\x13fileContentsRequest\
+    \x12X\n\x16file_contents_response\x18\x07\x20\x01(\x0b2\x20.hbb.CliprdrF\
+    ileContentsResponseH\0R\x14fileContentsResponseB\x07\n\x05union\"\x9c\
+    \x01\n\rSwitchDisplay\x12\x18\n\x07display\x18\x01\x20\x01(\x05R\x07disp\
+    lay\x12\x0c\n\x01x\x18\x02\x20\x01(\x11R\x01x\x12\x0c\n\x01y\x18\x03\x20\
+    \x01(\x11R\x01y\x12\x14\n\x05width\x18\x04\x20\x01(\x05R\x05width\x12\
+    \x16\n\x06height\x18\x05\x20\x01(\x05R\x06height\x12'\n\x0fcursor_embedd\
+    ed\x18\x06\x20\x01(\x08R\x0ecursorEmbedded\"\xc6\x01\n\x0ePermissionInfo\
+    \x12>\n\npermission\x18\x01\x20\x01(\x0e2\x1e.hbb.PermissionInfo.Permiss\
+    ionR\npermission\x12\x18\n\x07enabled\x18\x02\x20\x01(\x08R\x07enabled\"\
+    Z\n\nPermission\x12\x0c\n\x08Keyboard\x10\0\x12\r\n\tClipboard\x10\x02\
+    \x12\t\n\x05Audio\x10\x03\x12\x08\n\x04File\x10\x04\x12\x0b\n\x07Restart\
+    \x10\x05\x12\r\n\tRecording\x10\x06\"\xdc\x01\n\x0fVideoCodecState\x12\
+    \x1b\n\tscore_vpx\x18\x01\x20\x01(\x05R\x08scoreVpx\x12\x1d\n\nscore_h26\
+    4\x18\x02\x20\x01(\x05R\tscoreH264\x12\x1d\n\nscore_h265\x18\x03\x20\x01\
+    (\x05R\tscoreH265\x128\n\x06prefer\x18\x04\x20\x01(\x0e2\x20.hbb.VideoCo\
+    decState.PreferCodecR\x06prefer\"4\n\x0bPreferCodec\x12\x08\n\x04Auto\
+    \x10\0\x12\x07\n\x03VPX\x10\x01\x12\x08\n\x04H264\x10\x02\x12\x08\n\x04H\
+    265\x10\x03\"\x89\x06\n\rOptionMessage\x126\n\rimage_quality\x18\x01\x20\
+    \x01(\x0e2\x11.hbb.ImageQualityR\x0cimageQuality\x12R\n\x16lock_after_se\
+    ssion_end\x18\x02\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolOptionR\x13loc\
+    kAfterSessionEnd\x12K\n\x12show_remote_cursor\x18\x03\x20\x01(\x0e2\x1d.\
+    hbb.OptionMessage.BoolOptionR\x10showRemoteCursor\x12@\n\x0cprivacy_mode\
+    \x18\x04\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolOptionR\x0bprivacyMode\
+    \x12>\n\x0bblock_input\x18\x05\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolO\
+    ptionR\nblockInput\x120\n\x14custom_image_quality\x18\x06\x20\x01(\x05R\
+    \x12customImageQuality\x12B\n\rdisable_audio\x18\x07\x20\x01(\x0e2\x1d.h\
+    bb.OptionMessage.BoolOptionR\x0cdisableAudio\x12J\n\x11disable_clipboard\
+    \x18\x08\x20\x01(\x0e2\x1d.hbb.OptionMessage.BoolOptionR\x10disableClipb\
+    oard\x12O\n\x14enable_file_transfer\x18\t\x20\x01(\x0e2\x1d.hbb.OptionMe\
+    ssage.BoolOptionR\x12enableFileTransfer\x12@\n\x11video_codec_state\x18\
+    \n\x20\x01(\x0b2\x14.hbb.VideoCodecStateR\x0fvideoCodecState\x12\x1d\n\n\
+    custom_fps\x18\x0b\x20\x01(\x05R\tcustomFps\")\n\nBoolOption\x12\n\n\x06\
+    NotSet\x10\0\x12\x06\n\x02No\x10\x01\x12\x07\n\x03Yes\x10\x02\"\x86\x01\
+    \n\tTestDelay\x12\x12\n\x04time\x18\x01\x20\x01(\x03R\x04time\x12\x1f\n\
+    \x0bfrom_client\x18\x02\x20\x01(\x08R\nfromClient\x12\x1d\n\nlast_delay\
+    \x18\x03\x20\x01(\rR\tlastDelay\x12%\n\x0etarget_bitrate\x18\x04\x20\x01\
+    (\rR\rtargetBitrate\"\x9c\x01\n\tPublicKey\x12)\n\x10asymmetric_value\
+    \x18\x01\x20\x01(\x0cR\x0fasymmetricValue\x12'\n\x0fsymmetric_value\x18\
+    \x02\x20\x01(\x0cR\x0esymmetricValue\x12%\n\x0edirection_keys\x18\x03\
+    \x20\x01(\x08R\rdirectionKeys\x12\x14\n\x05rekey\x18\x04\x20\x01(\x08R\
+    \x05rekey\"\x1a\n\x08SignedId\x12\x0e\n\x02id\x18\x01\x20\x01(\x0cR\x02i\
+    d\"J\n\x0bAudioFormat\x12\x1f\n\x0bsample_rate\x18\x01\x20\x01(\rR\nsamp\
+    leRate\x12\x1a\n\x08channels\x18\x02\x20\x01(\rR\x08channels\">\n\nAudio\
+    Frame\x12\x12\n\x04data\x18\x01\x20\x01(\x0cR\x04data\x12\x1c\n\ttimesta\
+    mp\x18\x02\x20\x01(\x03R\ttimestamp\"d\n\nMessageBox\x12\x18\n\x07msgtyp\
+    e\x18\x01\x20\x01(\tR\x07msgtype\x12\x14\n\x05title\x18\x02\x20\x01(\tR\
     \x05title\x12\x12\n\x04text\x18\x03\x20\x01(\tR\x04text\x12\x12\n\x04lin\
     k\x18\x04\x20\x01(\tR\x04link\"\xac\x04\n\x10BackNotification\x12V\n\x12\
     privacy_mode_state\x18\x01\x20\x01(\x0e2&.hbb.BackNotification.PrivacyMo\
@@ -17215,12 +17433,13 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     B\x12\x07\n\x03Add\x10C\x12\x0c\n\x08Subtract\x10D\x12\x0b\n\x07Decimal\
     \x10E\x12\n\n\x06Divide\x10F\x12\n\n\x06Equals\x10G\x12\x0f\n\x0bNumpadE\
     nter\x10H\x12\n\n\x06RShift\x10I\x12\x0c\n\x08RControl\x10J\x12\x08\n\
-    \x04RAlt\x10K\x12\x0e\n\nCtrlAltDel\x10d\x12\x0e\n\nLockScreen\x10e*F\n\
-    \x08FileType\x12\x07\n\x03Dir\x10\0\x12\x0b\n\x07DirLink\x10\x02\x12\x0c\
-    \n\x08DirDrive\x10\x03\x12\x08\n\x04File\x10\x04\x12\x0c\n\x08FileLink\
-    \x10\x05*;\n\x0cImageQuality\x12\n\n\x06NotSet\x10\0\x12\x07\n\x03Low\
-    \x10\x02\x12\x0c\n\x08Balanced\x10\x03\x12\x08\n\x04Best\x10\x04b\x06pro\
-    to3\
+    \x04RAlt\x10K\x12\x0e\n\nCtrlAltDel\x10d\x12\x0e\n\nLockScreen\x10e*6\n\
+    \rCompressCodec\x12\x08\n\x04ZSTD\x10\0\x12\x07\n\x03LZ4\x10\x01\x12\x12\
+    \n\x0eNO_COMPRESSION\x10\x02*F\n\x08FileType\x12\x07\n\x03Dir\x10\0\x12\
+    \x0b\n\x07DirLink\x10\x02\x12\x0c\n\x08DirDrive\x10\x03\x12\x08\n\x04Fil\
+    e\x10\x04\x12\x0c\n\x08FileLink\x10\x05*;\n\x0cImageQuality\x12\n\n\x06N\
+    otSet\x10\0\x12\x07\n\x03Low\x10\x02\x12\x0c\n\x08Balanced\x10\x03\x12\
+    \x08\n\x04Best\x10\x04b\x06proto3\
 ";
 
 /// `FileDescriptorProto` object which was a source for this generated file
@@ -17306,9 +17525,10 @@ pub fn file_descriptor() -> &'static ::protobuf::reflect::FileDescriptor {
             messages.push(VoiceCallRequest::generated_message_descriptor_data());
             messages.push(VoiceCallResponse::generated_message_descriptor_data());
             messages.push(Message::generated_message_descriptor_data());
-            let mut enums = ::std::vec::Vec::with_capacity(9);
+            let mut enums = ::std::vec::Vec::with_capacity(10);
             enums.push(KeyboardMode::generated_enum_descriptor_data());
             enums.push(ControlKey::generated_enum_descriptor_data());
+            enums.push(CompressCodec::generated_enum_descriptor_data());
             enums.push(FileType::generated_enum_descriptor_data());
             enums.push(ImageQuality::generated_enum_descriptor_data());
             enums.push(permission_info::Permission::generated_enum_descriptor_data());
@@ -3592,6 +3592,462 @@ impl ::protobuf::reflect::ProtobufValue for OnlineResponse {
     type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
 }
 
+// @@protoc_insertion_point(message:hbb.RelayLoad)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct RelayLoad {
+    // message fields
+    // @@protoc_insertion_point(field:hbb.RelayLoad.relay_server)
+    pub relay_server: ::std::string::String,
+    // @@protoc_insertion_point(field:hbb.RelayLoad.sessions)
+    pub sessions: u32,
+    // @@protoc_insertion_point(field:hbb.RelayLoad.max_sessions)
+    pub max_sessions: u32,
+    // @@protoc_insertion_point(field:hbb.RelayLoad.bandwidth)
+    pub bandwidth: u64,
+    // @@protoc_insertion_point(field:hbb.RelayLoad.capacity)
+    pub capacity: u64,
+    // @@protoc_insertion_point(field:hbb.RelayLoad.time)
+    pub time: i64,
+    // special fields
+    // @@protoc_insertion_point(special_field:hbb.RelayLoad.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a RelayLoad {
+    fn default() -> &'a RelayLoad {
+        <RelayLoad as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl RelayLoad {
+    pub fn new() -> RelayLoad {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(6);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "relay_server",
+            |m: &RelayLoad| { &m.relay_server },
+            |m: &mut RelayLoad| { &mut m.relay_server },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "sessions",
+            |m: &RelayLoad| { &m.sessions },
+            |m: &mut RelayLoad| { &mut m.sessions },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "max_sessions",
+            |m: &RelayLoad| { &m.max_sessions },
+            |m: &mut RelayLoad| { &mut m.max_sessions },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "bandwidth",
+            |m: &RelayLoad| { &m.bandwidth },
+            |m: &mut RelayLoad| { &mut m.bandwidth },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "capacity",
+            |m: &RelayLoad| { &m.capacity },
+            |m: &mut RelayLoad| { &mut m.capacity },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "time",
+            |m: &RelayLoad| { &m.time },
+            |m: &mut RelayLoad| { &mut m.time },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<RelayLoad>(
+            "RelayLoad",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for RelayLoad {
+    const NAME: &'static str = "RelayLoad";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                10 => {
+                    self.relay_server = is.read_string()?;
+                },
+                16 => {
+                    self.sessions = is.read_uint32()?;
+                },
+                24 => {
+                    self.max_sessions = is.read_uint32()?;
+                },
+                32 => {
+                    self.bandwidth = is.read_uint64()?;
+                },
+                40 => {
+                    self.capacity = is.read_uint64()?;
+                },
+                48 => {
+                    self.time = is.read_int64()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if !self.relay_server.is_empty() {
+            my_size += ::protobuf::rt::string_size(1, &self.relay_server);
+        }
+        if self.sessions != 0 {
+            my_size += ::protobuf::rt::uint32_size(2, self.sessions);
+        }
+        if self.max_sessions != 0 {
+            my_size += ::protobuf::rt::uint32_size(3, self.max_sessions);
+        }
+        if self.bandwidth != 0 {
+            my_size += ::protobuf::rt::uint64_size(4, self.bandwidth);
+        }
+        if self.capacity != 0 {
+            my_size += ::protobuf::rt::uint64_size(5, self.capacity);
+        }
+        if self.time != 0 {
+            my_size += ::protobuf::rt::int64_size(6, self.time);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if !self.relay_server.is_empty() {
+            os.write_string(1, &self.relay_server)?;
+        }
+        if self.sessions != 0 {
+            os.write_uint32(2, self.sessions)?;
+        }
+        if self.max_sessions != 0 {
+            os.write_uint32(3, self.max_sessions)?;
+        }
+        if self.bandwidth != 0 {
+            os.write_uint64(4, self.bandwidth)?;
+        }
+        if self.capacity != 0 {
+            os.write_uint64(5, self.capacity)?;
+        }
+        if self.time != 0 {
+            os.write_int64(6, self.time)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> RelayLoad {
+        RelayLoad::new()
+    }
+
+    fn clear(&mut self) {
+        self.relay_server.clear();
+        self.sessions = 0;
+        self.max_sessions = 0;
+        self.bandwidth = 0;
+        self.capacity = 0;
+        self.time = 0;
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static RelayLoad {
+        static instance: RelayLoad = RelayLoad {
+            relay_server: ::std::string::String::new(),
+            sessions: 0,
+            max_sessions: 0,
+            bandwidth: 0,
+            capacity: 0,
+            time: 0,
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for RelayLoad {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("RelayLoad").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for RelayLoad {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for RelayLoad {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
+// @@protoc_insertion_point(message:hbb.RelayHeartbeat)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct RelayHeartbeat {
+    // message fields
+    // @@protoc_insertion_point(field:hbb.RelayHeartbeat.signed_load)
+    pub signed_load: ::bytes::Bytes,
+    // special fields
+    // @@protoc_insertion_point(special_field:hbb.RelayHeartbeat.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a RelayHeartbeat {
+    fn default() -> &'a RelayHeartbeat {
+        <RelayHeartbeat as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl RelayHeartbeat {
+    pub fn new() -> RelayHeartbeat {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(1);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "signed_load",
+            |m: &RelayHeartbeat| { &m.signed_load },
+            |m: &mut RelayHeartbeat| { &mut m.signed_load },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<RelayHeartbeat>(
+            "RelayHeartbeat",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for RelayHeartbeat {
+    const NAME: &'static str = "RelayHeartbeat";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                10 => {
+                    self.signed_load = is.read_tokio_bytes()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if !self.signed_load.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(1, &self.signed_load);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if !self.signed_load.is_empty() {
+            os.write_bytes(1, &self.signed_load)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> RelayHeartbeat {
+        RelayHeartbeat::new()
+    }
+
+    fn clear(&mut self) {
+        self.signed_load.clear();
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static RelayHeartbeat {
+        static instance: RelayHeartbeat = RelayHeartbeat {
+            signed_load: ::bytes::Bytes::new(),
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for RelayHeartbeat {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("RelayHeartbeat").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for RelayHeartbeat {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for RelayHeartbeat {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
+// @@protoc_insertion_point(message:hbb.RelayPeerChallenge)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct RelayPeerChallenge {
+    // message fields
+    // @@protoc_insertion_point(field:hbb.RelayPeerChallenge.nonce)
+    pub nonce: ::bytes::Bytes,
+    // special fields
+    // @@protoc_insertion_point(special_field:hbb.RelayPeerChallenge.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a RelayPeerChallenge {
+    fn default() -> &'a RelayPeerChallenge {
+        <RelayPeerChallenge as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl RelayPeerChallenge {
+    pub fn new() -> RelayPeerChallenge {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(1);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "nonce",
+            |m: &RelayPeerChallenge| { &m.nonce },
+            |m: &mut RelayPeerChallenge| { &mut m.nonce },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<RelayPeerChallenge>(
+            "RelayPeerChallenge",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for RelayPeerChallenge {
+    const NAME: &'static str = "RelayPeerChallenge";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                10 => {
+                    self.nonce = is.read_tokio_bytes()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if !self.nonce.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(1, &self.nonce);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if !self.nonce.is_empty() {
+            os.write_bytes(1, &self.nonce)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> RelayPeerChallenge {
+        RelayPeerChallenge::new()
+    }
+
+    fn clear(&mut self) {
+        self.nonce.clear();
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static RelayPeerChallenge {
+        static instance: RelayPeerChallenge = RelayPeerChallenge {
+            nonce: ::bytes::Bytes::new(),
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for RelayPeerChallenge {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("RelayPeerChallenge").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for RelayPeerChallenge {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for RelayPeerChallenge {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
 // @@protoc_insertion_point(message:hbb.RendezvousMessage)
 #[derive(PartialEq,Clone,Default,Debug)]
 pub struct RendezvousMessage {
@@ -4544,8 +5000,106 @@ impl RendezvousMessage {
         }
     }
 
+    // .hbb.RelayHeartbeat relay_heartbeat = 25;
+
+    pub fn relay_heartbeat(&self) -> &RelayHeartbeat {
+        match self.union {
+            ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(ref v)) => v,
+            _ => <RelayHeartbeat as ::protobuf::Message>::default_instance(),
+        }
+    }
+
+    pub fn clear_relay_heartbeat(&mut self) {
+        self.union = ::std::option::Option::None;
+    }
+
+    pub fn has_relay_heartbeat(&self) -> bool {
+        match self.union {
+            ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_relay_heartbeat(&mut self, v: RelayHeartbeat) {
+        self.union = ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_relay_heartbeat(&mut self) -> &mut RelayHeartbeat {
+        if let ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(_)) = self.union {
+        } else {
+            self.union = ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(RelayHeartbeat::new()));
+        }
+        match self.union {
+            ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_relay_heartbeat(&mut self) -> RelayHeartbeat {
+        if self.has_relay_heartbeat() {
+            match self.union.take() {
+                ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            RelayHeartbeat::new()
+        }
+    }
+
+    // .hbb.RelayPeerChallenge relay_peer_challenge = 26;
+
+    pub fn relay_peer_challenge(&self) -> &RelayPeerChallenge {
+        match self.union {
+            ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(ref v)) => v,
+            _ => <RelayPeerChallenge as ::protobuf::Message>::default_instance(),
+        }
+    }
+
+    pub fn clear_relay_peer_challenge(&mut self) {
+        self.union = ::std::option::Option::None;
+    }
+
+    pub fn has_relay_peer_challenge(&self) -> bool {
+        match self.union {
+            ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_relay_peer_challenge(&mut self, v: RelayPeerChallenge) {
+        self.union = ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_relay_peer_challenge(&mut self) -> &mut RelayPeerChallenge {
+        if let ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(_)) = self.union {
+        } else {
+            self.union = ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(RelayPeerChallenge::new()));
+        }
+        match self.union {
+            ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_relay_peer_challenge(&mut self) -> RelayPeerChallenge {
+        if self.has_relay_peer_challenge() {
+            match self.union.take() {
+                ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            RelayPeerChallenge::new()
+        }
+    }
+
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(19);
+        let mut fields = ::std::vec::Vec::with_capacity(21);
         let mut oneofs = ::std::vec::Vec::with_capacity(1);
         fields.push(::protobuf::reflect::rt::v2::make_oneof_message_has_get_mut_set_accessor::<_, RegisterPeer>(
             "register_peer",
@@ -4680,6 +5234,20 @@ impl RendezvousMessage {
             RendezvousMessage::mut_online_response,
             RendezvousMessage::set_online_response,
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_oneof_message_has_get_mut_set_accessor::<_, RelayHeartbeat>(
+            "relay_heartbeat",
+            RendezvousMessage::has_relay_heartbeat,
+            RendezvousMessage::relay_heartbeat,
+            RendezvousMessage::mut_relay_heartbeat,
+            RendezvousMessage::set_relay_heartbeat,
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_oneof_message_has_get_mut_set_accessor::<_, RelayPeerChallenge>(
+            "relay_peer_challenge",
+            RendezvousMessage::has_relay_peer_challenge,
+            RendezvousMessage::relay_peer_challenge,
+            RendezvousMessage::mut_relay_peer_challenge,
+            RendezvousMessage::set_relay_peer_challenge,
+        ));
         oneofs.push(rendezvous_message::Union::generated_oneof_descriptor_data());
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<RendezvousMessage>(
             "RendezvousMessage",
@@ -4756,6 +5324,12 @@ impl ::protobuf::Message for RendezvousMessage {
                 194 => {
                     self.union = ::std::option::Option::Some(rendezvous_message::Union::OnlineResponse(is.read_message()?));
                 },
+                202 => {
+                    self.union = ::std::option::Option::Some(rendezvous_message::Union::RelayHeartbeat(is.read_message()?));
+                },
+                210 => {
+                    self.union = ::std::option::Option::Some(rendezvous_message::Union::RelayPeerChallenge(is.read_message()?));
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -4846,6 +5420,14 @@ impl ::protobuf::Message for RendezvousMessage {
                     let len = v.compute_size();
                     my_size += 2 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
                 },
+                &rendezvous_message::Union::RelayHeartbeat(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 2 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
+                },
+                &rendezvous_message::Union::RelayPeerChallenge(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 2 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
+                },
             };
         }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
@@ -4913,6 +5495,12 @@ impl ::protobuf::Message for RendezvousMessage {
                 &rendezvous_message::Union::OnlineResponse(ref v) => {
                     ::protobuf::rt::write_message_field_with_cached_size(24, v, os)?;
                 },
+                &rendezvous_message::Union::RelayHeartbeat(ref v) => {
+                    ::protobuf::rt::write_message_field_with_cached_size(25, v, os)?;
+                },
+                &rendezvous_message::Union::RelayPeerChallenge(ref v) => {
+                    ::protobuf::rt::write_message_field_with_cached_size(26, v, os)?;
+                },
             };
         }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
@@ -4951,6 +5539,8 @@ impl ::protobuf::Message for RendezvousMessage {
         self.union = ::std::option::Option::None;
         self.union = ::std::option::Option::None;
         self.union = ::std::option::Option::None;
+        self.union = ::std::option::Option::None;
+        self.union = ::std::option::Option::None;
         self.special_fields.clear();
     }
 
@@ -5025,6 +5615,10 @@ pub mod rendezvous_message {
         OnlineRequest(super::OnlineRequest),
         // @@protoc_insertion_point(oneof_field:hbb.RendezvousMessage.online_response)
         OnlineResponse(super::OnlineResponse),
+        // @@protoc_insertion_point(oneof_field:hbb.RendezvousMessage.relay_heartbeat)
+        RelayHeartbeat(super::RelayHeartbeat),
+        // @@protoc_insertion_point(oneof_field:hbb.RendezvousMessage.relay_peer_challenge)
+        RelayPeerChallenge(super::RelayPeerChallenge),
     }
 
     impl ::protobuf::Oneof for Union {
@@ -5247,32 +5841,42 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     \x01(\tR\x08platform\x12\x12\n\x04misc\x18\x07\x20\x01(\tR\x04misc\"5\n\
     \rOnlineRequest\x12\x0e\n\x02id\x18\x01\x20\x01(\tR\x02id\x12\x14\n\x05p\
     eers\x18\x02\x20\x03(\tR\x05peers\"(\n\x0eOnlineResponse\x12\x16\n\x06st\
-    ates\x18\x01\x20\x01(\x0cR\x06states\"\xd2\t\n\x11RendezvousMessage\x128\
-    \n\rregister_peer\x18\x06\x20\x01(\x0b2\x11.hbb.RegisterPeerH\0R\x0cregi\
-    sterPeer\x12Q\n\x16register_peer_response\x18\x07\x20\x01(\x0b2\x19.hbb.\
-    RegisterPeerResponseH\0R\x14registerPeerResponse\x12E\n\x12punch_hole_re\
-    quest\x18\x08\x20\x01(\x0b2\x15.hbb.PunchHoleRequestH\0R\x10punchHoleReq\
-    uest\x12/\n\npunch_hole\x18\t\x20\x01(\x0b2\x0e.hbb.PunchHoleH\0R\tpunch\
-    Hole\x12<\n\x0fpunch_hole_sent\x18\n\x20\x01(\x0b2\x12.hbb.PunchHoleSent\
-    H\0R\rpunchHoleSent\x12H\n\x13punch_hole_response\x18\x0b\x20\x01(\x0b2\
-    \x16.hbb.PunchHoleResponseH\0R\x11punchHoleResponse\x12?\n\x10fetch_loca\
-    l_addr\x18\x0c\x20\x01(\x0b2\x13.hbb.FetchLocalAddrH\0R\x0efetchLocalAdd\
-    r\x12/\n\nlocal_addr\x18\r\x20\x01(\x0b2\x0e.hbb.LocalAddrH\0R\tlocalAdd\
-    r\x12>\n\x10configure_update\x18\x0e\x20\x01(\x0b2\x11.hbb.ConfigUpdateH\
-    \0R\x0fconfigureUpdate\x122\n\x0bregister_pk\x18\x0f\x20\x01(\x0b2\x0f.h\
-    bb.RegisterPkH\0R\nregisterPk\x12K\n\x14register_pk_response\x18\x10\x20\
-    \x01(\x0b2\x17.hbb.RegisterPkResponseH\0R\x12registerPkResponse\x12>\n\
-    \x0fsoftware_update\x18\x11\x20\x01(\x0b2\x13.hbb.SoftwareUpdateH\0R\x0e\
-    softwareUpdate\x128\n\rrequest_relay\x18\x12\x20\x01(\x0b2\x11.hbb.Reque\
-    stRelayH\0R\x0crequestRelay\x12;\n\x0erelay_response\x18\x13\x20\x01(\
-    \x0b2\x12.hbb.RelayResponseH\0R\rrelayResponse\x12?\n\x10test_nat_reques\
-    t\x18\x14\x20\x01(\x0b2\x13.hbb.TestNatRequestH\0R\x0etestNatRequest\x12\
-    B\n\x11test_nat_response\x18\x15\x20\x01(\x0b2\x14.hbb.TestNatResponseH\
-    \0R\x0ftestNatResponse\x12;\n\x0epeer_discovery\x18\x16\x20\x01(\x0b2\
-    \x12.hbb.PeerDiscoveryH\0R\rpeerDiscovery\x12;\n\x0eonline_request\x18\
-    \x17\x20\x01(\x0b2\x12.hbb.OnlineRequestH\0R\ronlineRequest\x12>\n\x0fon\
-    line_response\x18\x18\x20\x01(\x0b2\x13.hbb.OnlineResponseH\0R\x0eonline\
-    ResponseB\x07\n\x05union*J\n\x08ConnType\x12\x10\n\x0cDEFAULT_CONN\x10\0\
+    ates\x18\x01\x20\x01(\x0cR\x06states\"\xbb\x01\n\tRelayLoad\x12!\n\x0cre\
+    lay_server\x18\x01\x20\x01(\tR\x0brelayServer\x12\x1a\n\x08sessions\x18\
+    \x02\x20\x01(\rR\x08sessions\x12!\n\x0cmax_sessions\x18\x03\x20\x01(\rR\
+    \x0bmaxSessions\x12\x1c\n\tbandwidth\x18\x04\x20\x01(\x04R\tbandwidth\
+    \x12\x1a\n\x08capacity\x18\x05\x20\x01(\x04R\x08capacity\x12\x12\n\x04ti\
+    me\x18\x06\x20\x01(\x03R\x04time\"1\n\x0eRelayHeartbeat\x12\x1f\n\x0bsig\
+    ned_load\x18\x01\x20\x01(\x0cR\nsignedLoad\"*\n\x12RelayPeerChallenge\
+    \x12\x14\n\x05nonce\x18\x01\x20\x01(\x0cR\x05nonce\"\xdf\n\n\x11Rendezvo\
+    usMessage\x128\n\rregister_peer\x18\x06\x20\x01(\x0b2\x11.hbb.RegisterPe\
+    erH\0R\x0cregisterPeer\x12Q\n\x16register_peer_response\x18\x07\x20\x01(\
+    \x0b2\x19.hbb.RegisterPeerResponseH\0R\x14registerPeerResponse\x12E\n\
+    \x12punch_hole_request\x18\x08\x20\x01(\x0b2\x15.hbb.PunchHoleRequestH\0\
+    R\x10punchHoleRequest\x12/\n\npunch_hole\x18\t\x20\x01(\x0b2\x0e.hbb.Pun\
+    chHoleH\0R\tpunchHole\x12<\n\x0fpunch_hole_sent\x18\n\x20\x01(\x0b2\x12.\
+    hbb.PunchHoleSentH\0R\rpunchHoleSent\x12H\n\x13punch_hole_response\x18\
+    \x0b\x20\x01(\x0b2\x16.hbb.PunchHoleResponseH\0R\x11punchHoleResponse\
+    \x12?\n\x10fetch_local_addr\x18\x0c\x20\x01(\x0b2\x13.hbb.FetchLocalAddr\
+    H\0R\x0efetchLocalAddr\x12/\n\nlocal_addr\x18\r\x20\x01(\x0b2\x0e.hbb.Lo\
+    calAddrH\0R\tlocalAddr\x12>\n\x10configure_update\x18\x0e\x20\x01(\x0b2\
+    \x11.hbb.ConfigUpdateH\0R\x0fconfigureUpdate\x122\n\x0bregister_pk\x18\
+    \x0f\x20\x01(\x0b2\x0f.hbb.RegisterPkH\0R\nregisterPk\x12K\n\x14register\
+    _pk_response\x18\x10\x20\x01(\x0b2\x17.hbb.RegisterPkResponseH\0R\x12reg\
+    isterPkResponse\x12>\n\x0fsoftware_update\x18\x11\x20\x01(\x0b2\x13.hbb.\
+    SoftwareUpdateH\0R\x0esoftwareUpdate\x128\n\rrequest_relay\x18\x12\x20\
+    \x01(\x0b2\x11.hbb.RequestRelayH\0R\x0crequestRelay\x12;\n\x0erelay_resp\
+    onse\x18\x13\x20\x01(\x0b2\x12.hbb.RelayResponseH\0R\rrelayResponse\x12?\
+    \n\x10test_nat_request\x18\x14\x20\x01(\x0b2\x13.hbb.TestNatRequestH\0R\
+    \x0etestNatRequest\x12B\n\x11test_nat_response\x18\x15\x20\x01(\x0b2\x14\
+    .hbb.TestNatResponseH\0R\x0ftestNatResponse\x12;\n\x0epeer_discovery\x18\
+    \x16\x20\x01(\x0b2\x12.hbb.PeerDiscoveryH\0R\rpeerDiscovery\x12;\n\x0eon\
+    line_request\x18\x17\x20\x01(\x0b2\x12.hbb.OnlineRequestH\0R\ronlineRequ\
+    est\x12>\n\x0fonline_response\x18\x18\x20\x01(\x0b2\x13.hbb.OnlineRespon\
+    seH\0R\x0eonlineResponse\x12>\n\x0frelay_heartbeat\x18\x19\x20\x01(\x0b2\
+    \x13.hbb.RelayHeartbeatH\0R\x0erelayHeartbeat\x12K\n\x14relay_peer_chall\
+    enge\x18\x1a\x20\x01(\x0b2\x17.hbb.RelayPeerChallengeH\0R\x12relayPeerCh\
+    allengeB\x07\n\x05union*J\n\x08ConnType\x12\x10\n\x0cDEFAULT_CONN\x10\0\
     \x12\x11\n\rFILE_TRANSFER\x10\x01\x12\x10\n\x0cPORT_FORWARD\x10\x02\x12\
     \x07\n\x03RDP\x10\x03*9\n\x07NatType\x12\x0f\n\x0bUNKNOWN_NAT\x10\0\x12\
     \x0e\n\nASYMMETRIC\x10\x01\x12\r\n\tSYMMETRIC\x10\x02b\x06proto3\
@@ -5293,7 +5897,7 @@ pub fn file_descriptor() -> &'static ::protobuf::reflect::FileDescriptor {
     file_descriptor.get(|| {
         let generated_file_descriptor = generated_file_descriptor_lazy.get(|| {
             let mut deps = ::std::vec::Vec::with_capacity(0);
-            let mut messages = ::std::vec::Vec::with_capacity(20);
+            let mut messages = ::std::vec::Vec::with_capacity(23);
             messages.push(RegisterPeer::generated_message_descriptor_data());
             messages.push(RegisterPeerResponse::generated_message_descriptor_data());
             messages.push(PunchHoleRequest::generated_message_descriptor_data());
@@ -5313,6 +5917,9 @@ pub fn file_descriptor() -> &'static ::protobuf::reflect::FileDescriptor {
             messages.push(PeerDiscovery::generated_message_descriptor_data());
             messages.push(OnlineRequest::generated_message_descriptor_data());
             messages.push(OnlineResponse::generated_message_descriptor_data());
+            messages.push(RelayLoad::generated_message_descriptor_data());
+            messages.push(RelayHeartbeat::generated_message_descriptor_data());
+            messages.push(RelayPeerChallenge::generated_message_descriptor_data());
             messages.push(RendezvousMessage::generated_message_descriptor_data());
             let mut enums = ::std::vec::Vec::with_capacity(4);
             enums.push(ConnType::generated_enum_descriptor_data());
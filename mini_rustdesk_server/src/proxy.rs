@@ -0,0 +1,94 @@
+use crate::ResultType;
+use anyhow::bail;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const MAX_RESPONSE_HEADER: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyScheme {
+    Http,
+    Socks5,
+}
+
+/// A proxy given as `http://[user:password@]host:port` or
+/// `socks5://[user:password@]host:port`. Without a scheme, socks5 is assumed
+/// as before.
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    pub scheme: ProxyScheme,
+    pub addr: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl Proxy {
+    pub fn from_url(url: &str) -> ResultType<Self> {
+        let (scheme, rest) = match url.split_once("://") {
+            Some((scheme, rest)) => (scheme.to_lowercase(), rest),
+            None => ("socks5".to_owned(), url),
+        };
+        let scheme = match scheme.as_str() {
+            "http" => ProxyScheme::Http,
+            "socks5" | "socks5h" => ProxyScheme::Socks5,
+            _ => bail!("Unsupported proxy scheme: {}", scheme),
+        };
+        let rest = rest.trim_end_matches('/');
+        let (auth, addr) = match rest.rsplit_once('@') {
+            Some((auth, addr)) => (auth, addr),
+            None => ("", rest),
+        };
+        let (username, password) = auth.split_once(':').unwrap_or((auth, ""));
+        if addr.is_empty() {
+            bail!("Missing proxy address in {}", url);
+        }
+        let addr = if addr.contains(':') {
+            addr.to_owned()
+        } else {
+            match scheme {
+                ProxyScheme::Http => format!("{}:80", addr),
+                ProxyScheme::Socks5 => format!("{}:1080", addr),
+            }
+        };
+        Ok(Self {
+            scheme,
+            addr,
+            username: username.to_owned(),
+            password: password.to_owned(),
+        })
+    }
+}
+
+/// Asks the HTTP proxy behind `stream` to tunnel to `target` with CONNECT,
+/// using Basic authentication if `username` is not empty.
+pub async fn http_connect(
+    stream: &mut TcpStream,
+    target: &str,
+    username: &str,
+    password: &str,
+) -> ResultType<()> {
+    let mut req = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", target);
+    if !username.is_empty() {
+        let auth = base64::encode(format!("{}:{}", username, password));
+        req += &format!("Proxy-Authorization: Basic {}\r\n", auth);
+    }
+    req += "\r\n";
+    stream.write_all(req.as_bytes()).await?;
+    // read byte by byte so that nothing after the header is consumed
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_RESPONSE_HEADER {
+            bail!("HTTP proxy response header too long");
+        }
+        header.push(stream.read_u8().await?);
+    }
+    let header = String::from_utf8_lossy(&header);
+    let status = header.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(()),
+        Some("407") => bail!("HTTP proxy authentication required"),
+        _ => bail!("HTTP proxy refused CONNECT to {}: {}", target, status),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::common::{get_arg_or, timeout};
+use crate::ResultType;
+use anyhow::bail;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    net::TcpStream,
+};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_CMD_LOCAL: u8 = 0x20;
+const V2_CMD_PROXY: u8 = 0x21;
+const V2_FAM_TCP4: u8 = 0x11;
+const V2_FAM_TCP6: u8 = 0x21;
+const READ_TIMEOUT: u64 = 3_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Expects a HAProxy PROXY protocol header (v1 or v2) on every tcp and
+/// websocket connection if `proxy-protocol` is `Y`. Only enable this behind a
+/// load balancer that always sends one, otherwise clients can spoof their ip.
+pub fn init() {
+    let enabled = get_arg_or("proxy-protocol", "N".to_owned()).to_uppercase() == "Y";
+    ENABLED.store(enabled, Ordering::SeqCst);
+    log::info!("PROXY protocol: {}", enabled);
+}
+
+/// Consumes the PROXY header from `stream` if enabled and returns the real
+/// client address, or `addr` for health checks sent by the balancer itself.
+pub async fn accept(stream: &mut TcpStream, addr: SocketAddr) -> ResultType<SocketAddr> {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return Ok(addr);
+    }
+    Ok(timeout(READ_TIMEOUT, read_header(stream))
+        .await??
+        .unwrap_or(addr))
+}
+
+async fn read_header(stream: &mut (impl AsyncRead + Unpin)) -> ResultType<Option<SocketAddr>> {
+    let mut buf = [0u8; 12];
+    stream.read_exact(&mut buf[..V1_PREFIX.len()]).await?;
+    if &buf[..V1_PREFIX.len()] == V1_PREFIX {
+        return read_v1(stream).await;
+    }
+    stream.read_exact(&mut buf[V1_PREFIX.len()..]).await?;
+    if buf[..] == V2_SIGNATURE[..] {
+        return read_v2(stream).await;
+    }
+    bail!("Missing PROXY protocol header")
+}
+
+async fn read_v1(stream: &mut (impl AsyncRead + Unpin)) -> ResultType<Option<SocketAddr>> {
+    // read byte by byte so that nothing after the header is consumed
+    let mut line = V1_PREFIX.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LEN {
+            bail!("PROXY v1 header too long");
+        }
+        line.push(stream.read_u8().await?);
+    }
+    let line = std::str::from_utf8(&line)?.trim_end();
+    let parts: Vec<&str> = line.split(' ').collect();
+    match parts.get(1) {
+        Some(&"TCP4") | Some(&"TCP6") if parts.len() == 6 => {
+            let ip: IpAddr = parts[2].parse()?;
+            let port: u16 = parts[4].parse()?;
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        Some(&"UNKNOWN") => Ok(None),
+        _ => bail!("Invalid PROXY v1 header: {}", line),
+    }
+}
+
+async fn read_v2(stream: &mut (impl AsyncRead + Unpin)) -> ResultType<Option<SocketAddr>> {
+    let cmd = stream.read_u8().await?;
+    let fam = stream.read_u8().await?;
+    let len = stream.read_u16().await? as usize;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await?;
+    match cmd {
+        V2_CMD_LOCAL => return Ok(None),
+        V2_CMD_PROXY => {}
+        _ => bail!("Invalid PROXY v2 command: {:#x}", cmd),
+    }
+    match fam {
+        V2_FAM_TCP4 if len >= 12 => {
+            let ip = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+            let port = u16::from_be_bytes([body[8], body[9]]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(ip), port)))
+        }
+        V2_FAM_TCP6 if len >= 36 => {
+            let mut ip = [0u8; 16];
+            ip.copy_from_slice(&body[..16]);
+            let port = u16::from_be_bytes([body[32], body[33]]);
+            Ok(Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port)))
+        }
+        // udp, unix sockets or unspecified
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse(bytes: &[u8]) -> ResultType<Option<SocketAddr>> {
+        let mut stream = bytes;
+        let res = read_header(&mut stream).await;
+        // nothing after the header may be consumed
+        if res.is_ok() {
+            assert_eq!(stream, b"rest");
+        }
+        res
+    }
+
+    fn v2_header(cmd: u8, fam: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.extend([cmd, fam]);
+        bytes.extend((body.len() as u16).to_be_bytes());
+        bytes.extend(body);
+        bytes.extend(b"rest");
+        bytes
+    }
+
+    #[tokio::test]
+    async fn v1_headers() {
+        assert_eq!(
+            parse(b"PROXY TCP4 1.2.3.4 5.6.7.8 1234 21117\r\nrest").await.unwrap(),
+            Some("1.2.3.4:1234".parse().unwrap())
+        );
+        assert_eq!(
+            parse(b"PROXY TCP6 ::1 ::2 1234 21117\r\nrest").await.unwrap(),
+            Some("[::1]:1234".parse().unwrap())
+        );
+        assert_eq!(parse(b"PROXY UNKNOWN\r\nrest").await.unwrap(), None);
+        assert!(parse(b"PROXY TCP4 1.2.3.4 5.6.7.8 1234\r\nrest").await.is_err());
+        assert!(parse(b"PROXY TCP4 1.2.3.4 5.6.7.8 1234 21117").await.is_err());
+        let mut long = b"PROXY UNKNOWN ".to_vec();
+        long.resize(200, b'x');
+        long.extend(b"\r\nrest");
+        assert!(parse(&long).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v2_headers() {
+        let tcp4 = [1, 2, 3, 4, 5, 6, 7, 8, 0x04, 0xd2, 0x52, 0x7d];
+        assert_eq!(
+            parse(&v2_header(V2_CMD_PROXY, V2_FAM_TCP4, &tcp4)).await.unwrap(),
+            Some("1.2.3.4:1234".parse().unwrap())
+        );
+        let mut tcp6 = [0u8; 36];
+        tcp6[15] = 1;
+        tcp6[31] = 2;
+        tcp6[32..34].copy_from_slice(&1234u16.to_be_bytes());
+        assert_eq!(
+            parse(&v2_header(V2_CMD_PROXY, V2_FAM_TCP6, &tcp6)).await.unwrap(),
+            Some("[::1]:1234".parse().unwrap())
+        );
+        // health checks of the balancer itself
+        assert_eq!(parse(&v2_header(V2_CMD_LOCAL, 0, &[])).await.unwrap(), None);
+        // udp is not relayed, the connection keeps its own address
+        assert_eq!(parse(&v2_header(V2_CMD_PROXY, 0x12, &tcp4)).await.unwrap(), None);
+        assert!(parse(&v2_header(0x22, V2_FAM_TCP4, &tcp4)).await.is_err());
+        let truncated = v2_header(V2_CMD_PROXY, V2_FAM_TCP4, &tcp4);
+        assert!(parse(&truncated[..truncated.len() - 8]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn bad_signature() {
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\nrest").await.is_err());
+        let mut bytes = v2_header(V2_CMD_PROXY, V2_FAM_TCP4, &[0; 12]);
+        bytes[11] = b'X';
+        assert!(parse(&bytes).await.is_err());
+        assert!(parse(b"PROX").await.is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::common::{sleep, timeout, try_into_v4, AddrMangle};
+use crate::rendezvous::{PunchHole, PunchHoleResponse};
+use crate::tcp::{new_listener, new_socket, FramedStream};
+use crate::udp::FramedSocket;
+use crate::ResultType;
+use anyhow::bail;
+use bytes::BytesMut;
+use std::net::SocketAddr;
+use tokio::time::{interval, Duration, Instant};
+use tokio_socks::TargetAddr;
+
+const TCP_CONNECT_TIMEOUT: u64 = 1_000;
+const TCP_RETRY_INTERVAL: f32 = 0.1; // in seconds
+const UDP_PROBE_INTERVAL: u64 = 100; // in ms
+// sent a few more times once the peer is heard, so that it hears us too
+const UDP_EXTRA_PROBES: usize = 3;
+pub const UDP_PROBE: &[u8] = b"\0punch";
+
+/// A udp socket with a punched mapping to `peer`.
+pub struct UdpFlow {
+    pub socket: FramedSocket,
+    pub peer: SocketAddr,
+    /// The first packet from the peer, if it was data rather than a probe.
+    pub pending: Option<BytesMut>,
+}
+
+fn decode_peer_addr(socket_addr: &[u8]) -> ResultType<SocketAddr> {
+    let addr = AddrMangle::decode(socket_addr);
+    if addr.ip().is_unspecified() || addr.port() == 0 {
+        bail!("Invalid peer address");
+    }
+    Ok(addr)
+}
+
+pub fn peer_addr_of_punch_hole(ph: &PunchHole) -> ResultType<SocketAddr> {
+    decode_peer_addr(&ph.socket_addr)
+}
+
+pub fn peer_addr_of_punch_hole_response(ph: &PunchHoleResponse) -> ResultType<SocketAddr> {
+    decode_peer_addr(&ph.socket_addr)
+}
+
+fn is_peer(addr: SocketAddr, peer: SocketAddr) -> bool {
+    // the port may differ behind a symmetric NAT
+    try_into_v4(addr).ip() == try_into_v4(peer).ip()
+}
+
+/// TCP simultaneous open towards `peer`. `local_addr` must be the address
+/// (with a fixed port) the peer learned from the rendezvous server. Both
+/// sides keep connecting from that port while also listening on it, so
+/// whichever SYN makes it through the NATs first yields the stream.
+pub async fn punch_tcp(
+    local_addr: SocketAddr,
+    peer: SocketAddr,
+    ms_timeout: u64,
+) -> ResultType<FramedStream> {
+    let listener = new_listener(local_addr, true).await?;
+    let accept = async {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            if is_peer(addr, peer) {
+                return Ok::<_, anyhow::Error>(stream);
+            }
+            log::debug!("Punch listener ignored connection from {}", addr);
+        }
+    };
+    let connect = async {
+        loop {
+            if let Ok(Ok(stream)) = timeout(
+                TCP_CONNECT_TIMEOUT,
+                new_socket(local_addr, true)?.connect(peer),
+            )
+            .await
+            {
+                return Ok::<_, anyhow::Error>(stream);
+            }
+            sleep(TCP_RETRY_INTERVAL).await;
+        }
+    };
+    let stream = timeout(ms_timeout, async {
+        tokio::select! {
+            res = accept => res,
+            res = connect => res,
+        }
+    })
+    .await??;
+    stream.set_nodelay(true).ok();
+    let addr = stream.local_addr()?;
+    Ok(FramedStream::from(stream, addr))
+}
+
+/// UDP hole punching towards `peer` from `local_addr`, which must be the
+/// address the peer learned from the rendezvous server. Probes are sent
+/// until anything arrives from the peer.
+pub async fn punch_udp(
+    local_addr: SocketAddr,
+    peer: SocketAddr,
+    ms_timeout: u64,
+) -> ResultType<UdpFlow> {
+    let mut socket = FramedSocket::new_reuse(local_addr, true, 0).await?;
+    let deadline = Instant::now() + Duration::from_millis(ms_timeout);
+    let mut timer = interval(Duration::from_millis(UDP_PROBE_INTERVAL));
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => bail!("Udp punch to {} timed out", peer),
+            _ = timer.tick() => socket.send_raw(UDP_PROBE, peer).await?,
+            res = socket.next() => match res {
+                Some(Ok((bytes, TargetAddr::Ip(addr)))) if is_peer(addr, peer) => {
+                    for _ in 0..UDP_EXTRA_PROBES {
+                        socket.send_raw(UDP_PROBE, addr).await?;
+                    }
+                    let pending = if &bytes[..] == UDP_PROBE { None } else { Some(bytes) };
+                    return Ok(UdpFlow { socket, peer: addr, pending });
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+                None => bail!("Udp socket closed"),
+            },
+        }
+    }
+}
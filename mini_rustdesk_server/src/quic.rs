@@ -0,0 +1,124 @@
+use crate::ResultType;
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{interval, Duration},
+};
+
+pub use quinn::Endpoint;
+
+pub const ALPN: &[u8] = b"rustdesk";
+const RELOAD_INTERVAL: u64 = 10; // in seconds
+const IDLE_TIMEOUT: u64 = 30_000; // in ms
+const KEEP_ALIVE: u64 = 10; // in seconds
+// keep-alives stop the idle timeout, bound the wait for the stream like the
+// wait for the first frame of a TCP connection
+const ACCEPT_TIMEOUT: u64 = 30_000; // in ms
+
+/// The first bidirectional stream of a QUIC connection, carrying the same
+/// length-prefixed frames as a plain TCP connection.
+pub struct QuicStream {
+    // keeps the connection open as long as the stream is in use
+    _conn: quinn::Connection,
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
+}
+
+fn make_config(tls: &Arc<tokio_rustls::rustls::ServerConfig>) -> quinn::ServerConfig {
+    let mut crypto = (**tls).clone();
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    crypto.max_early_data_size = 0;
+    let mut config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    let mut transport = quinn::TransportConfig::default();
+    transport
+        .max_idle_timeout(Some(quinn::VarInt::from_u32(IDLE_TIMEOUT as _).into()))
+        .keep_alive_interval(Some(Duration::from_secs(KEEP_ALIVE)))
+        .max_concurrent_uni_streams(0u8.into());
+    config.transport_config(Arc::new(transport));
+    config
+}
+
+/// Binds a QUIC endpoint on udp `port` with the certificate of `tls::init`.
+/// Returns `None` if TLS is not configured, since QUIC cannot run without it.
+pub fn listen(port: u16) -> ResultType<Option<Endpoint>> {
+    let tls = match crate::tls::server_config() {
+        Some(tls) => tls,
+        None => return Ok(None),
+    };
+    let addr = SocketAddr::from(([0u16; 8], port));
+    let endpoint = Endpoint::server(make_config(&tls), addr)?;
+    let endpoint2 = endpoint.clone();
+    tokio::spawn(async move {
+        let mut current = tls;
+        let mut timer = interval(Duration::from_secs(RELOAD_INTERVAL));
+        loop {
+            timer.tick().await;
+            if let Some(tls) = crate::tls::server_config() {
+                if Arc::ptr_eq(&tls, &current) {
+                    continue;
+                }
+                endpoint2.set_server_config(Some(make_config(&tls)));
+                log::info!("QUIC certificate reloaded");
+                current = tls;
+            }
+        }
+    });
+    Ok(Some(endpoint))
+}
+
+/// Completes the handshake of an incoming connection and waits for the
+/// client to open its bidirectional stream, closing the connection if that
+/// does not happen within `ACCEPT_TIMEOUT`.
+pub async fn accept(connecting: quinn::Connecting) -> ResultType<(QuicStream, SocketAddr)> {
+    let conn = connecting.await?;
+    let addr = conn.remote_address();
+    let (send, recv) = match crate::common::timeout(ACCEPT_TIMEOUT, conn.accept_bi()).await {
+        Ok(res) => res?,
+        Err(err) => {
+            conn.close(0u32.into(), b"timeout");
+            return Err(err.into());
+        }
+    };
+    Ok((
+        QuicStream {
+            _conn: conn,
+            send,
+            recv,
+        },
+        addr,
+    ))
+}
+
+impl AsyncRead for QuicStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().recv).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for QuicStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().send).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().send).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().send).poll_shutdown(cx)
+    }
+}
@@ -1,31 +1,646 @@
+use anyhow::bail;
 use async_trait::async_trait;
-use sodiumoxide::crypto::sign;
+use sodiumoxide::{
+    crypto::{auth, hash::sha256, sign},
+    randombytes::randombytes,
+};
 use std::{
     collections::{HashMap, HashSet},
-    io::prelude::*,
     io::Error,
-    net::SocketAddr,
-    sync::atomic::{AtomicUsize, Ordering},
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use futures_util::{sink::SinkExt, stream::StreamExt};
 use tokio::{
     self,
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
     net::{TcpListener, TcpStream},
-    sync::{Mutex, RwLock},
+    sync::{Mutex, Notify, RwLock},
     time::{interval, Duration},
 };
 
-use protobuf::Message as _;
+use protobuf::{Enum as _, Message as _};
 use crate::rendezvous::*;
 use bytes::{Bytes, BytesMut};
-use crate::tcp::{listen_any, FramedStream};
+use crate::tcp::{listen_any, DynTcpStream, FramedStream};
+use crate::tls::MaybeTlsStream;
+use crate::common::{arg_name, get_arg, get_arg_or, get_server_sk, normalize_relay_addr, try_into_v4};
+use crate::config::RENDEZVOUS_PORT;
+use crate::database::{self, RelaySession};
 
 use crate::ResultType;
 
+struct PendingPeer {
+    serial: usize,
+    addr: SocketAddr,
+    id: String,
+    conn_type: i32,
+    secure: bool,
+    stream: Box<dyn StreamTrait>,
+    _guard: IpConnGuard,
+}
+
+/// Relay policy for one `ConnType`, configured with `POLICY-<CONN-TYPE>`, e.g.
+/// `POLICY-PORT-FORWARD=deny` or `POLICY-FILE-TRANSFER=bandwidth=8,duration=3600`
+/// for at most 8 Mb/s (8 * 1024 * 1024 bit/s) and one hour. `secure` only
+/// pairs sessions where both sides declared `RequestRelay.secure`.
+#[derive(Debug, Clone, Copy)]
+struct ConnPolicy {
+    allow: bool,
+    secure_only: bool,
+    bandwidth: usize,  // in bit/s, configured in Mb/s, 0 for unlimited
+    max_duration: u64, // in seconds, 0 for unlimited
+}
+
+impl Default for ConnPolicy {
+    fn default() -> Self {
+        Self {
+            allow: true,
+            secure_only: false,
+            bandwidth: 0,
+            max_duration: 0,
+        }
+    }
+}
+
+/// A session being relayed, for `status` and `kick` over ipc.
+struct ActiveSession {
+    addr: SocketAddr,
+    peer_id: String,
+    conn_type: i32,
+    started: Instant,
+    traffic: Arc<Traffic>,
+    kick: Arc<Notify>,
+}
+
+struct IpConns {
+    active: usize,
+    window_start: Instant,
+    window_count: usize,
+}
+
 lazy_static::lazy_static! {
-    static ref PEERS: Mutex<HashMap<String, Box<dyn StreamTrait>>> = Default::default();
+    static ref PEERS: Mutex<HashMap<String, PendingPeer>> = Default::default();
+    static ref IP_CONNS: std::sync::Mutex<HashMap<IpAddr, IpConns>> = Default::default();
+    static ref RELAY_PEERS: RwLock<HashSet<String>> = Default::default();
+    static ref RELAY_PEER_IPS: std::sync::RwLock<HashSet<IpAddr>> = Default::default();
+    static ref RELAY_PEER_KEY: RwLock<Option<auth::Key>> = Default::default();
+    static ref AUDIT_DB: RwLock<Option<database::Database>> = Default::default();
+    static ref CONN_POLICIES: RwLock<HashMap<i32, ConnPolicy>> = Default::default();
+    static ref SESSIONS: std::sync::Mutex<HashMap<String, ActiveSession>> = Default::default();
+}
+
+static MAX_PENDING: AtomicUsize = AtomicUsize::new(10_000);
+static MAX_CONNS_PER_IP: AtomicUsize = AtomicUsize::new(64);
+static MAX_CONNS_PER_SEC_PER_IP: AtomicUsize = AtomicUsize::new(20);
+static PENDING_SERIAL: AtomicUsize = AtomicUsize::new(0);
+static ACTIVE_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_BYTES: AtomicU64 = AtomicU64::new(0);
+static CAPACITY: AtomicUsize = AtomicUsize::new(0); // in bit/s, 0 for unknown
+static MAX_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+static SECURE_ONLY: AtomicBool = AtomicBool::new(false);
+const IP_CONNS_PRUNE_THRESHOLD: usize = 65_536;
+const RELAY_PEER_TOKEN_PREFIX: &str = "relay:";
+const RELAY_PEER_NONCE_LEN: usize = 32;
+// links from peer relays carry many sessions each
+const RELAY_PEER_CONN_FACTOR: usize = 16;
+const RELAY_PEER_CONNECT_TIMEOUT: u64 = 10_000;
+const DEFAULT_AUDIT_RETENTION_DAYS: i64 = 90;
+const HEARTBEAT_INTERVAL: u64 = 10; // in seconds
+
+fn check_params() {
+    let tmp = get_arg("max-pending").parse::<usize>().unwrap_or(0);
+    if tmp > 0 {
+        MAX_PENDING.store(tmp, Ordering::SeqCst);
+    }
+    log::info!("MAX_PENDING: {}", MAX_PENDING.load(Ordering::SeqCst));
+    let tmp = get_arg("max-conns-per-ip").parse::<usize>().unwrap_or(0);
+    if tmp > 0 {
+        MAX_CONNS_PER_IP.store(tmp, Ordering::SeqCst);
+    }
+    log::info!("MAX_CONNS_PER_IP: {}", MAX_CONNS_PER_IP.load(Ordering::SeqCst));
+    let tmp = get_arg("max-conns-per-sec-per-ip")
+        .parse::<usize>()
+        .unwrap_or(0);
+    if tmp > 0 {
+        MAX_CONNS_PER_SEC_PER_IP.store(tmp, Ordering::SeqCst);
+    }
+    log::info!(
+        "MAX_CONNS_PER_SEC_PER_IP: {}",
+        MAX_CONNS_PER_SEC_PER_IP.load(Ordering::SeqCst)
+    );
+    // in Mb/s
+    let tmp = get_arg("capacity").parse::<f64>().unwrap_or(0.);
+    if tmp > 0. {
+        CAPACITY.store((tmp * 1024. * 1024.) as _, Ordering::SeqCst);
+    }
+    log::info!("CAPACITY: {}Mb/s", CAPACITY.load(Ordering::SeqCst) as f64 / 1024. / 1024.);
+    let tmp = get_arg("max-sessions").parse::<usize>().unwrap_or(0);
+    if tmp > 0 {
+        MAX_SESSIONS.store(tmp, Ordering::SeqCst);
+    }
+    log::info!("MAX_SESSIONS: {}", MAX_SESSIONS.load(Ordering::SeqCst));
+    SECURE_ONLY.store(
+        get_arg_or("relay-secure-only", "N".to_owned()).to_uppercase() == "Y",
+        Ordering::SeqCst,
+    );
+    log::info!("SECURE_ONLY: {}", SECURE_ONLY.load(Ordering::SeqCst));
+}
+
+/// Changes a limit of `check_params` or a relay policy at runtime. `name` is
+/// spelled like the argument or its environment variable.
+async fn set_param(name: &str, value: &str) -> ResultType<()> {
+    match arg_name(name).as_str() {
+        "MAX-PENDING" => MAX_PENDING.store(value.parse()?, Ordering::SeqCst),
+        "MAX-CONNS-PER-IP" => MAX_CONNS_PER_IP.store(value.parse()?, Ordering::SeqCst),
+        "MAX-CONNS-PER-SEC-PER-IP" => {
+            MAX_CONNS_PER_SEC_PER_IP.store(value.parse()?, Ordering::SeqCst)
+        }
+        // in Mb/s
+        "CAPACITY" => CAPACITY.store(
+            (value.parse::<f64>()? * 1024. * 1024.) as _,
+            Ordering::SeqCst,
+        ),
+        "MAX-SESSIONS" => MAX_SESSIONS.store(value.parse()?, Ordering::SeqCst),
+        "RELAY-SECURE-ONLY" => {
+            SECURE_ONLY.store(value.to_uppercase() == "Y", Ordering::SeqCst)
+        }
+        arg => {
+            let conn_type = ConnType::VALUES
+                .iter()
+                .find(|x| arg_name(&format!("policy-{:?}", x)) == arg)
+                .ok_or_else(|| anyhow::anyhow!("Unknown config {}", name))?;
+            let policy = parse_conn_policy(value);
+            log::info!("{:?} policy: {:?}", conn_type, policy);
+            CONN_POLICIES.write().await.insert(conn_type.value(), policy);
+        }
+    }
+    log::info!("{} set to {} over ipc", name, value);
+    Ok(())
+}
+
+fn status() -> serde_json::Value {
+    let sessions: Vec<serde_json::Value> = SESSIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(uuid, s)| {
+            serde_json::json!({
+                "uuid": uuid,
+                "addr": s.addr.to_string(),
+                "peer_id": s.peer_id,
+                "conn_type": s.conn_type,
+                "duration": s.started.elapsed().as_secs(),
+                "bytes_up": s.traffic.up.load(Ordering::Relaxed),
+                "bytes_down": s.traffic.down.load(Ordering::Relaxed),
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "active_sessions": ACTIVE_SESSIONS.load(Ordering::Relaxed),
+        "max_sessions": MAX_SESSIONS.load(Ordering::Relaxed),
+        "total_bytes": TOTAL_BYTES.load(Ordering::Relaxed),
+        "capacity": CAPACITY.load(Ordering::Relaxed),
+        "sessions": sessions,
+    })
+}
+
+#[cfg(unix)]
+async fn handle_ipc(cmd: crate::ipc::Command) -> crate::ipc::Response {
+    use crate::ipc::{Command, Response};
+    match cmd {
+        Command::Status => {
+            let mut status = status();
+            status["pending"] = PEERS.lock().await.len().into();
+            Response::ok(status)
+        }
+        Command::Kick { uuid } => {
+            let kick = SESSIONS.lock().unwrap().get(&uuid).map(|x| x.kick.clone());
+            if let Some(kick) = kick {
+                kick.notify_one();
+            } else if PEERS.lock().await.remove(&uuid).is_none() {
+                return Response::err(format!("No session {uuid}"));
+            }
+            log::info!("Relay session {} kicked over ipc", uuid);
+            Response::ok(serde_json::Value::Null)
+        }
+        Command::Config { name, value } => match set_param(&name, &value).await {
+            Ok(()) => Response::ok(serde_json::Value::Null),
+            Err(err) => Response::err(err),
+        },
+    }
+}
+
+/// Serves local control commands on the unix socket `ipc-path`, if set.
+fn check_ipc() {
+    #[cfg(unix)]
+    {
+        let path = get_arg("ipc-path");
+        if path.is_empty() {
+            return;
+        }
+        tokio::spawn(async move {
+            if let Err(err) = crate::ipc::serve(&path, handle_ipc).await {
+                log::error!("Ipc listener on {} failed: {}", path, err);
+            }
+        });
+    }
+}
+
+fn check_heartbeat(sk: Option<sign::SecretKey>) {
+    let servers: Vec<String> = get_arg("rendezvous-servers")
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            if x.contains(':') {
+                x.to_owned()
+            } else {
+                format!("{x}:{RENDEZVOUS_PORT}")
+            }
+        })
+        .collect();
+    if servers.is_empty() {
+        return;
+    }
+    let sk = match sk {
+        Some(sk) => sk,
+        None => {
+            log::error!("Relay heartbeat needs the private key, heartbeat disabled");
+            return;
+        }
+    };
+    let relay_server = get_arg("relay-address");
+    log::info!("Heartbeat to {:?} as {:?}", servers, relay_server);
+    for server in servers {
+        let relay_server = relay_server.clone();
+        let sk = sk.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = send_heartbeats(&server, &relay_server, &sk).await {
+                    log::warn!("Heartbeat to {} failed: {}", server, err);
+                }
+                crate::common::sleep(HEARTBEAT_INTERVAL as _).await;
+            }
+        });
+    }
+}
+
+/// Reports our load to one rendezvous server every `HEARTBEAT_INTERVAL`
+/// seconds, until the connection fails.
+async fn send_heartbeats(server: &str, relay_server: &str, sk: &sign::SecretKey) -> ResultType<()> {
+    let mut stream = FramedStream::new(server, None, RELAY_PEER_CONNECT_TIMEOUT).await?;
+    stream.set_send_timeout(RELAY_PEER_CONNECT_TIMEOUT);
+    let mut timer = interval(Duration::from_secs(HEARTBEAT_INTERVAL));
+    let mut last_bytes = TOTAL_BYTES.load(Ordering::Relaxed);
+    let mut last_time = Instant::now();
+    loop {
+        timer.tick().await;
+        let bytes = TOTAL_BYTES.load(Ordering::Relaxed);
+        let elapsed = last_time.elapsed().as_secs_f64().max(1.);
+        let bandwidth = (bytes.saturating_sub(last_bytes) as f64 * 8. / elapsed) as u64;
+        last_bytes = bytes;
+        last_time = Instant::now();
+        let load = RelayLoad {
+            relay_server: relay_server.to_owned(),
+            sessions: ACTIVE_SESSIONS.load(Ordering::Relaxed) as _,
+            max_sessions: MAX_SESSIONS.load(Ordering::Relaxed) as _,
+            bandwidth,
+            capacity: CAPACITY.load(Ordering::Relaxed) as _,
+            time: unix_secs(),
+            ..Default::default()
+        };
+        let mut msg_out = RendezvousMessage::new();
+        msg_out.set_relay_heartbeat(RelayHeartbeat {
+            signed_load: sign::sign(&load.write_to_bytes()?, sk).into(),
+            ..Default::default()
+        });
+        stream.send(&msg_out).await?;
+    }
+}
+
+async fn check_conn_policies() {
+    let mut policies = HashMap::new();
+    for conn_type in ConnType::VALUES {
+        let name = format!("policy-{:?}", conn_type).to_lowercase();
+        let value = get_arg(&name);
+        if value.is_empty() {
+            continue;
+        }
+        let policy = parse_conn_policy(&value);
+        log::info!("{:?} policy: {:?}", conn_type, policy);
+        policies.insert(conn_type.value(), policy);
+    }
+    *CONN_POLICIES.write().await = policies;
+}
+
+fn parse_conn_policy(value: &str) -> ConnPolicy {
+    let mut policy = ConnPolicy::default();
+    for item in value.split(',').map(|x| x.trim().to_lowercase()) {
+        match item.split_once('=') {
+            None if item == "deny" => policy.allow = false,
+            None if item == "allow" => policy.allow = true,
+            None if item == "secure" => policy.secure_only = true,
+            // in Mb/s, kept in bit/s
+            Some(("bandwidth", v)) => {
+                policy.bandwidth = (v.parse::<f64>().unwrap_or(0.) * 1024. * 1024.) as _
+            }
+            Some(("duration", v)) => policy.max_duration = v.parse::<u64>().unwrap_or(0),
+            _ => log::warn!("Unknown relay policy item: {}", item),
+        }
+    }
+    policy
+}
+
+async fn get_conn_policy(conn_type: i32) -> ConnPolicy {
+    let mut policy = CONN_POLICIES
+        .read()
+        .await
+        .get(&conn_type)
+        .copied()
+        .unwrap_or_default();
+    if SECURE_ONLY.load(Ordering::SeqCst) {
+        policy.secure_only = true;
+    }
+    policy
+}
+
+async fn check_audit_log() {
+    if get_arg_or("relay-audit", "Y".to_owned()).to_uppercase() == "N" {
+        log::info!("Relay audit log disabled");
+        return;
+    }
+    let days = get_arg("relay-audit-retention-days")
+        .parse::<i64>()
+        .unwrap_or(DEFAULT_AUDIT_RETENTION_DAYS);
+    let db = match database::Database::shared(&database::get_db_path()).await {
+        Ok(db) => db,
+        Err(err) => {
+            log::error!("Failed to open relay audit database: {}", err);
+            return;
+        }
+    };
+    log::info!("RELAY_AUDIT_RETENTION_DAYS: {}", days);
+    *AUDIT_DB.write().await = Some(db.clone());
+    if days > 0 {
+        tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(3600));
+            loop {
+                timer.tick().await;
+                match db.delete_relay_sessions_older_than(days).await {
+                    Ok(n) if n > 0 => log::info!("Purged {} expired relay sessions", n),
+                    Ok(_) => {}
+                    Err(err) => log::error!("Failed to purge relay sessions: {}", err),
+                }
+            }
+        });
+    }
+}
+
+fn record_session(session: RelaySession) {
+    tokio::spawn(async move {
+        if let Some(db) = AUDIT_DB.read().await.as_ref() {
+            if let Err(err) = db.insert_relay_session(&session).await {
+                log::error!("Failed to record relay session {}: {}", session.uuid, err);
+            }
+        }
+    });
+}
+
+fn unix_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+async fn check_relay_peers() {
+    let key = get_arg("relay-peer-key");
+    let peers: HashSet<String> = get_arg("relay-peers")
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(normalize_relay_addr)
+        .collect();
+    if peers.is_empty() {
+        return;
+    }
+    if key.is_empty() {
+        log::error!("relay-peers is set without relay-peer-key, relay chaining disabled");
+        return;
+    }
+    let mut ips = HashSet::new();
+    for peer in peers.iter() {
+        match tokio::net::lookup_host(peer).await {
+            Ok(addrs) => ips.extend(addrs.map(|x| try_into_v4(x).ip())),
+            Err(err) => log::warn!("Failed to resolve relay peer {}: {}", peer, err),
+        }
+    }
+    log::info!("RELAY_PEERS: {:?}", peers);
+    *RELAY_PEER_IPS.write().unwrap() = ips;
+    *RELAY_PEERS.write().await = peers;
+    *RELAY_PEER_KEY.write().await = Some(auth::Key(sha256::hash(key.as_bytes()).0));
+}
+
+fn relay_peer_tag(key: &auth::Key, nonce: &[u8], uuid: &str) -> auth::Tag {
+    let mut input = nonce.to_vec();
+    input.extend_from_slice(uuid.as_bytes());
+    auth::authenticate(&input, key)
+}
+
+/// The forwarding side of a relay peer link: announces itself with a bare
+/// token prefix, then answers the peer's challenge with a tag over its
+/// nonce and the uuid.
+async fn authenticate_to_relay_peer(link: &mut FramedStream, rf: &mut RequestRelay) -> ResultType<()> {
+    let key = match RELAY_PEER_KEY.read().await.clone() {
+        Some(key) => key,
+        None => bail!("No relay peer key"),
+    };
+    let mut msg_out = RendezvousMessage::new();
+    msg_out.set_request_relay(RequestRelay {
+        uuid: rf.uuid.clone(),
+        token: RELAY_PEER_TOKEN_PREFIX.to_owned(),
+        ..Default::default()
+    });
+    link.send(&msg_out).await?;
+    let nonce = match link.next_timeout(RELAY_PEER_CONNECT_TIMEOUT).await {
+        Some(Ok(bytes)) => match RendezvousMessage::parse_from_bytes(&bytes)?.union {
+            Some(rendezvous_message::Union::RelayPeerChallenge(c)) => c.nonce,
+            _ => bail!("Expected RelayPeerChallenge"),
+        },
+        Some(Err(err)) => return Err(err.into()),
+        None => bail!("Relay peer challenge timeout"),
+    };
+    let tag = relay_peer_tag(&key, &nonce, &rf.uuid);
+    rf.token = format!("{RELAY_PEER_TOKEN_PREFIX}{}", base64::encode(tag.0));
+    Ok(())
+}
+
+/// The receiving side: `stream` announced itself as a peer relay in `rf`.
+/// Sends it a fresh nonce and returns its actual request if that carries a
+/// tag over the nonce made with the shared relay peer key. Every nonce is
+/// only good for this one connection, so a captured token can not be
+/// replayed.
+///
+/// The link is only authenticated at setup, the frames relayed over it
+/// afterwards carry no integrity protection of their own and rely on the
+/// end-to-end encryption between the clients, like any other relayed stream.
+async fn challenge_relay_peer(
+    stream: &mut impl StreamTrait,
+    addr: SocketAddr,
+    rf: &RequestRelay,
+) -> Option<RequestRelay> {
+    let key = RELAY_PEER_KEY.read().await.clone()?;
+    let nonce = randombytes(RELAY_PEER_NONCE_LEN);
+    let mut msg_out = RendezvousMessage::new();
+    msg_out.set_relay_peer_challenge(RelayPeerChallenge {
+        nonce: nonce.clone().into(),
+        ..Default::default()
+    });
+    stream.send_raw(msg_out.write_to_bytes().ok()?.into()).await.ok()?;
+    let bytes = match crate::common::timeout(RELAY_PEER_CONNECT_TIMEOUT, stream.recv()).await {
+        Ok(Some(Ok(bytes))) => bytes,
+        _ => return None,
+    };
+    let rf2 = match RendezvousMessage::parse_from_bytes(&bytes).ok()?.union {
+        Some(rendezvous_message::Union::RequestRelay(rf2)) if rf2.uuid == rf.uuid => rf2,
+        _ => return None,
+    };
+    let tag = rf2
+        .token
+        .strip_prefix(RELAY_PEER_TOKEN_PREFIX)
+        .and_then(|x| base64::decode(x).ok())
+        .and_then(|x| auth::Tag::from_slice(&x));
+    let mut input = nonce;
+    input.extend_from_slice(rf2.uuid.as_bytes());
+    match tag {
+        Some(tag) if auth::verify(&tag, &input, &key) => Some(rf2),
+        _ => {
+            log::warn!("Relay peer authentication of {} failed", addr);
+            None
+        }
+    }
+}
+
+async fn is_relay_peer(relay_server: &str) -> bool {
+    !relay_server.is_empty()
+        && RELAY_PEERS
+            .read()
+            .await
+            .contains(&normalize_relay_addr(relay_server))
+}
+
+/// Hands a relay request over to the peer relay named in `rf.relay_server`,
+/// where the other side is waiting, and relays the link like a paired stream.
+async fn forward_to_relay_peer(stream: impl StreamTrait, addr: SocketAddr, rf: &RequestRelay) {
+    let policy = get_conn_policy(rf.conn_type.value()).await;
+    if !policy.allow {
+        log::info!("Relay request {} from {} denied by {:?} policy", rf.uuid, addr, rf.conn_type);
+        return;
+    }
+    if policy.secure_only && !rf.secure {
+        log::info!("Relay request {} from {} refused, not secure", rf.uuid, addr);
+        return;
+    }
+    let relay_server = normalize_relay_addr(&rf.relay_server);
+    let relay_addr = match tokio::net::lookup_host(&relay_server).await.map(|mut x| x.next()) {
+        Ok(Some(addr)) => addr,
+        _ => {
+            log::warn!("Failed to resolve relay peer {}", relay_server);
+            return;
+        }
+    };
+    let mut link = match FramedStream::new(relay_addr, None, RELAY_PEER_CONNECT_TIMEOUT).await {
+        Ok(link) => link,
+        Err(err) => {
+            log::warn!("Failed to connect to relay peer {}: {}", relay_server, err);
+            return;
+        }
+    };
+    let mut forwarded = RequestRelay {
+        id: rf.id.clone(),
+        uuid: rf.uuid.clone(),
+        socket_addr: rf.socket_addr.clone(),
+        secure: rf.secure,
+        conn_type: rf.conn_type,
+        ..Default::default()
+    };
+    if let Err(err) = authenticate_to_relay_peer(&mut link, &mut forwarded).await {
+        log::warn!("Failed to authenticate to relay peer {}: {}", relay_server, err);
+        return;
+    }
+    let mut msg_out = RendezvousMessage::new();
+    msg_out.set_request_relay(forwarded);
+    if let Err(err) = link.send(&msg_out).await {
+        log::warn!("Failed to forward {} to relay peer {}: {}", rf.uuid, relay_server, err);
+        return;
+    }
+    log::info!("Relay request {} from {} forwarded to {}", rf.uuid, addr, relay_server);
+    let session = RelaySession {
+        uuid: rf.uuid.clone(),
+        peer_id: rf.id.clone(),
+        ip: try_into_v4(addr).ip().to_string(),
+        peer_ip: try_into_v4(relay_addr).ip().to_string(),
+        conn_type: rf.conn_type.value(),
+        secure: rf.secure,
+        ..Default::default()
+    };
+    relay_pair(stream, Box::new(link), addr, session, policy).await;
+}
+
+/// Releases the per-IP connection slot taken by `try_acquire_ip` when dropped.
+struct IpConnGuard(IpAddr);
+
+impl Drop for IpConnGuard {
+    fn drop(&mut self) {
+        let mut conns = IP_CONNS.lock().unwrap();
+        if let Some(c) = conns.get_mut(&self.0) {
+            c.active = c.active.saturating_sub(1);
+            if c.active == 0 && c.window_start.elapsed().as_secs() >= 1 {
+                conns.remove(&self.0);
+            }
+        }
+    }
+}
+
+fn try_acquire_ip(addr: SocketAddr) -> Option<IpConnGuard> {
+    let ip = try_into_v4(addr).ip();
+    let factor = if RELAY_PEER_IPS.read().unwrap().contains(&ip) {
+        RELAY_PEER_CONN_FACTOR
+    } else {
+        1
+    };
+    let mut conns = IP_CONNS.lock().unwrap();
+    if conns.len() >= IP_CONNS_PRUNE_THRESHOLD {
+        conns.retain(|_, c| c.active > 0 || c.window_start.elapsed().as_secs() < 1);
+    }
+    let c = conns.entry(ip).or_insert_with(|| IpConns {
+        active: 0,
+        window_start: Instant::now(),
+        window_count: 0,
+    });
+    if c.window_start.elapsed().as_secs() >= 1 {
+        c.window_start = Instant::now();
+        c.window_count = 0;
+    }
+    if c.window_count >= MAX_CONNS_PER_SEC_PER_IP.load(Ordering::SeqCst) * factor {
+        log::warn!("Too many connections per second from {}", ip);
+        return None;
+    }
+    c.window_count += 1;
+    if c.active >= MAX_CONNS_PER_IP.load(Ordering::SeqCst) * factor {
+        log::warn!("Too many concurrent connections from {}", ip);
+        return None;
+    }
+    c.active += 1;
+    Some(IpConnGuard(ip))
 }
 
 async fn io_loop(listener: TcpListener, listener2: TcpListener, key: &str) {
@@ -59,6 +674,24 @@ async fn io_loop(listener: TcpListener, listener2: TcpListener, key: &str) {
     }
 }
 
+async fn quic_loop(endpoint: crate::quic::Endpoint, key: &str) {
+    while let Some(connecting) = endpoint.accept().await {
+        let guard = match try_acquire_ip(connecting.remote_address()) {
+            Some(guard) => guard,
+            None => continue,
+        };
+        let key = key.to_owned();
+        tokio::spawn(async move {
+            match crate::quic::accept(connecting).await {
+                Ok((stream, addr)) => {
+                    make_pair_(FramedStream::from(stream, addr), addr, &key, guard).await;
+                }
+                Err(err) => log::debug!("QUIC handshake failed: {}", err),
+            }
+        });
+    }
+    log::error!("QUIC endpoint closed");
+}
 
 async fn handle_connection(
     stream: TcpStream,
@@ -68,17 +701,47 @@ async fn handle_connection(
 ) {
     let key = key.to_owned();
     tokio::spawn(async move {
-        allow_err!(make_pair(stream, addr, &key, ws).await);
+        let mut stream = stream;
+        // per-ip limits apply to the real client behind a load balancer
+        let addr = match crate::proxy_protocol::accept(&mut stream, addr).await {
+            Ok(addr) => addr,
+            Err(err) => {
+                log::warn!("Failed to read PROXY header from {}: {}", addr, err);
+                return;
+            }
+        };
+        let guard = match try_acquire_ip(addr) {
+            Some(guard) => guard,
+            None => return,
+        };
+        allow_err!(make_pair(stream, addr, &key, ws, guard).await);
     });
 }
 
 #[tokio::main(flavor = "multi_thread")]
 pub async fn start(port: &str, key: &str) -> ResultType<()> {
-    let key = get_server_sk(key);
+    let (key, sk) = get_server_sk(key);
+    check_params();
+    check_heartbeat(sk);
+    check_relay_peers().await;
+    check_audit_log().await;
+    check_conn_policies().await;
+    crate::tls::init().await?;
+    crate::proxy_protocol::init();
+    check_ipc();
     let port: u16 = port.parse()?;
     log::info!("Listening on tcp :{}", port);
     let port2 = port + 2;
     log::info!("Listening on websocket :{}", port2);
+    let quic_port: u16 = get_arg_or("quic-port", port.to_string()).parse()?;
+    match crate::quic::listen(quic_port)? {
+        Some(endpoint) => {
+            log::info!("Listening on quic :{}", quic_port);
+            let key = key.clone();
+            tokio::spawn(async move { quic_loop(endpoint, &key).await });
+        }
+        None => log::info!("QUIC disabled, it requires tls-cert and tls-key"),
+    }
     let main_task = async move {
         loop {
             log::info!("Start");
@@ -97,16 +760,19 @@ async fn make_pair(
     addr: SocketAddr,
     key: &str,
     ws: bool,
+    guard: IpConnGuard,
 ) -> ResultType<()> {
+    let stream = crate::tls::accept(stream).await?;
     if ws {
         make_pair_(
             tokio_tungstenite::accept_async(stream).await?,
             addr,
-            key
+            key,
+            guard,
         )
         .await;
     } else {
-        make_pair_(FramedStream::from(stream, addr), addr, key).await;
+        make_pair_(FramedStream::from(stream, addr), addr, key, guard).await;
     }
     Ok(())
 }
@@ -116,7 +782,7 @@ trait StreamTrait: Send + Sync + 'static {
     async fn recv(&mut self) -> Option<Result<BytesMut, Error>>;
     async fn send_raw(&mut self, bytes: Bytes) -> ResultType<()>;
     fn is_ws(&self) -> bool;
-    fn set_raw(&mut self);
+    fn into_framed(self: Box<Self>) -> Option<FramedStream>;
 }
 
 #[async_trait]
@@ -133,13 +799,13 @@ impl StreamTrait for FramedStream {
         false
     }
 
-    fn set_raw(&mut self) {
-        self.set_raw();
+    fn into_framed(self: Box<Self>) -> Option<FramedStream> {
+        Some(*self)
     }
 }
 
 #[async_trait]
-impl StreamTrait for tokio_tungstenite::WebSocketStream<TcpStream> {
+impl StreamTrait for tokio_tungstenite::WebSocketStream<MaybeTlsStream> {
     async fn recv(&mut self) -> Option<Result<BytesMut, Error>> {
         if let Some(msg) = self.next().await {
             match msg {
@@ -151,7 +817,7 @@ impl StreamTrait for tokio_tungstenite::WebSocketStream<TcpStream> {
                         _ => Some(Ok(BytesMut::new())),
                     }
                 }
-                Err(err) => Some(Err(Error::new(std::io::ErrorKind::Other, err.to_string()))),
+                Err(err) => Some(Err(Error::other(err.to_string()))),
             }
         } else {
             None
@@ -168,38 +834,138 @@ impl StreamTrait for tokio_tungstenite::WebSocketStream<TcpStream> {
         true
     }
 
-    fn set_raw(&mut self) {}
+    fn into_framed(self: Box<Self>) -> Option<FramedStream> {
+        None
+    }
 }
 
 
-async fn make_pair_(stream: impl StreamTrait, addr: SocketAddr, key: &str) {
+async fn make_pair_(
+    stream: impl StreamTrait,
+    addr: SocketAddr,
+    key: &str,
+    guard: IpConnGuard,
+) {
     let mut stream = stream;
     if let Ok(Some(Ok(bytes))) = crate::common::timeout(30_000, stream.recv()).await {
         if let Ok(msg_in) = RendezvousMessage::parse_from_bytes(&bytes) {
             if let Some(rendezvous_message::Union::RequestRelay(rf)) = msg_in.union {
-                if !key.is_empty() && rf.licence_key != key {
+                let from_relay_peer = rf.token == RELAY_PEER_TOKEN_PREFIX;
+                let rf = if from_relay_peer {
+                    match challenge_relay_peer(&mut stream, addr, &rf).await {
+                        Some(rf) => rf,
+                        None => return,
+                    }
+                } else {
+                    rf
+                };
+                if !from_relay_peer && !key.is_empty() && rf.licence_key != key {
                     return;
                 }
                 if !rf.uuid.is_empty() {
-                    let mut peer = PEERS.lock().await.remove(&rf.uuid);
-                    if let Some(peer) = peer.as_mut() {
+                    if !from_relay_peer && is_relay_peer(&rf.relay_server).await {
+                        forward_to_relay_peer(stream, addr, &rf).await;
+                        return;
+                    }
+                    let mut peers = PEERS.lock().await;
+                    if let Some(peer) = peers.remove(&rf.uuid) {
+                        drop(peers);
+                        let PendingPeer {
+                            addr: peer_addr,
+                            id: peer_id,
+                            conn_type: peer_conn_type,
+                            secure: peer_secure,
+                            stream: peer,
+                            _guard: peer_guard,
+                            ..
+                        } = peer;
                         log::info!("Relayrequest {} from {} got paired", rf.uuid, addr);
-                        if !stream.is_ws() && !peer.is_ws() {
-                            peer.set_raw();
-                            stream.set_raw();
-                            log::info!("Both are raw");
-                        }
-                        if let Err(err) = relay(&mut stream, peer).await
-                        {
-                            log::info!("Relay of {} closed: {}", addr, err);
+                        // the side that knows what it is connecting for decides
+                        let conn_type = if rf.conn_type.value() == ConnType::DEFAULT_CONN.value() {
+                            peer_conn_type
                         } else {
-                            log::info!("Relay of {} closed", addr);
+                            rf.conn_type.value()
+                        };
+                        let policy = get_conn_policy(conn_type).await;
+                        if !policy.allow {
+                            log::info!("Relay request {} from {} denied by policy", rf.uuid, addr);
+                            return;
                         }
+                        let secure = rf.secure && peer_secure;
+                        if policy.secure_only && !secure {
+                            log::info!(
+                                "Relay request {} from {} refused, both sides must be secure",
+                                rf.uuid,
+                                addr
+                            );
+                            return;
+                        }
+                        let session = RelaySession {
+                            uuid: rf.uuid.clone(),
+                            peer_id: if rf.id.is_empty() { peer_id } else { rf.id.clone() },
+                            ip: try_into_v4(addr).ip().to_string(),
+                            peer_ip: try_into_v4(peer_addr).ip().to_string(),
+                            conn_type,
+                            secure,
+                            ..Default::default()
+                        };
+                        relay_pair(stream, peer, addr, session, policy).await;
+                        drop(peer_guard);
+                        drop(guard);
+                    } else if !get_conn_policy(rf.conn_type.value()).await.allow {
+                        log::info!(
+                            "Relay request {} from {} denied by {:?} policy",
+                            rf.uuid,
+                            addr,
+                            rf.conn_type
+                        );
+                    } else if !rf.secure
+                        && get_conn_policy(rf.conn_type.value()).await.secure_only
+                    {
+                        log::info!("Relay request {} from {} refused, not secure", rf.uuid, addr);
+                    } else if peers.len() >= MAX_PENDING.load(Ordering::SeqCst) {
+                        log::warn!(
+                            "Pending relay table full ({}), refused {} from {}",
+                            peers.len(),
+                            rf.uuid,
+                            addr
+                        );
                     } else {
                         log::info!("New relay request {} from {}", rf.uuid, addr);
-                        PEERS.lock().await.insert(rf.uuid.clone(), Box::new(stream));
+                        let serial = PENDING_SERIAL.fetch_add(1, Ordering::SeqCst);
+                        let started_at = unix_secs();
+                        peers.insert(
+                            rf.uuid.clone(),
+                            PendingPeer {
+                                serial,
+                                addr,
+                                id: rf.id.clone(),
+                                conn_type: rf.conn_type.value(),
+                                secure: rf.secure,
+                                stream: Box::new(stream),
+                                _guard: guard,
+                            },
+                        );
+                        drop(peers);
                         crate::common::sleep(30.).await;
-                        PEERS.lock().await.remove(&rf.uuid);
+                        let mut peers = PEERS.lock().await;
+                        // only drop our own entry, the uuid may have been paired and reused
+                        if peers.get(&rf.uuid).map(|p| p.serial) == Some(serial) {
+                            if let Some(p) = peers.remove(&rf.uuid) {
+                                log::info!("Relay request {} from {} expired", rf.uuid, p.addr);
+                                record_session(RelaySession {
+                                    uuid: rf.uuid.clone(),
+                                    peer_id: p.id,
+                                    ip: try_into_v4(p.addr).ip().to_string(),
+                                    conn_type: p.conn_type,
+                                    secure: p.secure,
+                                    started_at,
+                                    ended_at: unix_secs(),
+                                    close_reason: "Timeout".to_owned(),
+                                    ..Default::default()
+                                });
+                            }
+                        }
                     }
                 }
             }
@@ -207,18 +973,151 @@ async fn make_pair_(stream: impl StreamTrait, addr: SocketAddr, key: &str) {
     }
 }
 
+async fn relay_pair(
+    stream: impl StreamTrait,
+    peer: Box<dyn StreamTrait>,
+    addr: SocketAddr,
+    session: RelaySession,
+    policy: ConnPolicy,
+) {
+    let mut stream = stream;
+    let mut peer = peer;
+    let mut session = session;
+    session.started_at = unix_secs();
+    ACTIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+    let traffic = Arc::new(Traffic::default());
+    let kick = Arc::new(Notify::new());
+    // a reused uuid replaces the entry, which is then only removed by its owner
+    SESSIONS.lock().unwrap().insert(
+        session.uuid.clone(),
+        ActiveSession {
+            addr,
+            peer_id: session.peer_id.clone(),
+            conn_type: session.conn_type,
+            started: Instant::now(),
+            traffic: traffic.clone(),
+            kick: kick.clone(),
+        },
+    );
+    let relay_fut = async {
+        if !stream.is_ws() && !peer.is_ws() {
+            log::info!("Both are raw");
+            match (Box::new(stream).into_framed(), peer.into_framed()) {
+                // copy_bidirectional can not be throttled
+                (Some(stream), Some(peer)) if policy.bandwidth == 0 => {
+                    relay_raw(stream, peer, &traffic, &policy).await
+                }
+                (Some(mut stream), Some(mut peer)) => {
+                    stream.set_raw();
+                    peer.set_raw();
+                    let mut peer: Box<dyn StreamTrait> = Box::new(peer);
+                    relay(&mut stream, &mut peer, &traffic, &policy).await
+                }
+                _ => Err(anyhow::Error::msg("Not raw streams")),
+            }
+        } else {
+            relay(&mut stream, &mut peer, &traffic, &policy).await
+        }
+    };
+    let res = tokio::select! {
+        res = relay_fut => res,
+        _ = kick.notified() => Err(anyhow::Error::msg("Kicked")),
+    };
+    {
+        let mut sessions = SESSIONS.lock().unwrap();
+        if sessions.get(&session.uuid).map(|x| Arc::ptr_eq(&x.kick, &kick)) == Some(true) {
+            sessions.remove(&session.uuid);
+        }
+    }
+    ACTIVE_SESSIONS.fetch_sub(1, Ordering::Relaxed);
+    let up = traffic.up.load(Ordering::Relaxed);
+    let down = traffic.down.load(Ordering::Relaxed);
+    if let Err(err) = &res {
+        log::info!("Relay of {} closed: {}, up {} down {} bytes", addr, err, up, down);
+    } else {
+        log::info!("Relay of {} closed, up {} down {} bytes", addr, up, down);
+    }
+    session.ended_at = unix_secs();
+    session.bytes_up = up as _;
+    session.bytes_down = down as _;
+    session.close_reason = match res {
+        Ok(()) => "normal".to_owned(),
+        Err(err) => err.to_string(),
+    };
+    record_session(session);
+}
+
+/// Bytes forwarded by one relay session, `up` from the paired stream to the
+/// pending peer and `down` the other way.
+#[derive(Default)]
+struct Traffic {
+    up: AtomicU64,
+    down: AtomicU64,
+}
+
+fn count_bytes(bytes: &AtomicU64, n: usize) {
+    bytes.fetch_add(n as _, Ordering::Relaxed);
+    TOTAL_BYTES.fetch_add(n as _, Ordering::Relaxed);
+}
+
+/// Keeps the average rate of a relay session under `ConnPolicy::bandwidth`.
+struct Limiter {
+    bandwidth: usize,
+    start: Instant,
+    bytes: usize,
+}
+
+impl Limiter {
+    fn new(bandwidth: usize) -> Self {
+        Self {
+            bandwidth,
+            start: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    async fn consume(&mut self, n: usize) {
+        if self.bandwidth == 0 {
+            return;
+        }
+        self.bytes += n;
+        let expected = Duration::from_secs_f64((self.bytes * 8) as f64 / self.bandwidth as f64);
+        let elapsed = self.start.elapsed();
+        if expected > elapsed {
+            tokio::time::sleep(expected - elapsed).await;
+        } else if elapsed.as_secs() >= 3 {
+            // do not let an idle period build up a burst
+            self.start = Instant::now();
+            self.bytes = 0;
+        }
+    }
+}
+
+fn check_max_duration(start: Instant, policy: &ConnPolicy) -> ResultType<()> {
+    if policy.max_duration > 0 && start.elapsed().as_secs() >= policy.max_duration {
+        return Err(anyhow::Error::msg("Max duration exceeded"));
+    }
+    Ok(())
+}
+
 async fn relay(
     stream: &mut impl StreamTrait,
     peer: &mut Box<dyn StreamTrait>,
+    traffic: &Traffic,
+    policy: &ConnPolicy,
 ) -> ResultType<()> {
     let mut timer = interval(Duration::from_secs(3));
     let mut last_recv_time = std::time::Instant::now();
+    let start = Instant::now();
+    let mut limiter = Limiter::new(policy.bandwidth);
     loop {
         tokio::select! {
             res = peer.recv() => {
                 if let Some(Ok(bytes)) = res {
                     last_recv_time = std::time::Instant::now();
                     if !bytes.is_empty() {
+                        count_bytes(&traffic.down, bytes.len());
+                        limiter.consume(bytes.len()).await;
                         stream.send_raw(bytes.into()).await?;
                     }
                 } else {
@@ -229,6 +1128,8 @@ async fn relay(
                 if let Some(Ok(bytes)) = res {
                     last_recv_time = std::time::Instant::now();
                     if !bytes.is_empty() {
+                        count_bytes(&traffic.up, bytes.len());
+                        limiter.consume(bytes.len()).await;
                         peer.send_raw(bytes.into()).await?;
                     }
                 } else {
@@ -239,29 +1140,129 @@ async fn relay(
                 if last_recv_time.elapsed().as_secs() > 30 {
                     return Err(anyhow::Error::msg("Timeout"));
                 }
+                check_max_duration(start, policy)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Forwards two raw TCP streams with `copy_bidirectional`, bypassing the
+/// codec. Bytes the codec had already buffered are flushed to the other side
+/// first.
+async fn relay_raw(
+    stream: FramedStream,
+    peer: FramedStream,
+    traffic: &Traffic,
+    policy: &ConnPolicy,
+) -> ResultType<()> {
+    let (stream, stream_buf) = stream.into_raw();
+    let (peer, peer_buf) = peer.into_raw();
+    let start = Instant::now();
+    let last_active = AtomicU64::new(0);
+    let mut stream = MeteredStream {
+        inner: stream,
+        bytes: &traffic.up,
+        last_active: &last_active,
+        start,
+    };
+    let mut peer = MeteredStream {
+        inner: peer,
+        bytes: &traffic.down,
+        last_active: &last_active,
+        start,
+    };
+    if !stream_buf.is_empty() {
+        count_bytes(&traffic.up, stream_buf.len());
+        peer.write_all(&stream_buf).await?;
+    }
+    if !peer_buf.is_empty() {
+        count_bytes(&traffic.down, peer_buf.len());
+        stream.write_all(&peer_buf).await?;
+    }
+    let mut timer = interval(Duration::from_secs(3));
+    let copy = tokio::io::copy_bidirectional(&mut stream, &mut peer);
+    tokio::pin!(copy);
+    loop {
+        tokio::select! {
+            res = &mut copy => {
+                res?;
+                break;
+            }
+            _ = timer.tick() => {
+                let now = start.elapsed().as_millis() as u64;
+                if now.saturating_sub(last_active.load(Ordering::Relaxed)) > 30_000 {
+                    return Err(anyhow::Error::msg("Timeout"));
+                }
+                check_max_duration(start, policy)?;
             }
         }
     }
     Ok(())
 }
 
-fn get_server_sk(key: &str) -> String {
-    let mut key = key.to_owned();
-    if let Ok(sk) = base64::decode(&key) {
-        if sk.len() == sign::SECRETKEYBYTES {
-            log::info!("The key is a crypto private key");
-            key = base64::encode(&sk[(sign::SECRETKEYBYTES / 2)..]);
+/// Counts the bytes read from `inner` and records when it was last active,
+/// in ms since `start`, for the idle timeout of `relay_raw`.
+struct MeteredStream<'a> {
+    inner: DynTcpStream,
+    bytes: &'a AtomicU64,
+    last_active: &'a AtomicU64,
+    start: Instant,
+}
+
+impl AsyncRead for MeteredStream<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let n = buf.filled().len() - before;
+        if n > 0 {
+            count_bytes(self.bytes, n);
+            self.last_active
+                .store(self.start.elapsed().as_millis() as _, Ordering::Relaxed);
         }
+        res
     }
+}
 
-    if key == "-" || key == "_" {
-        let (pk, _) = crate::common::gen_sk(300);
-        key = pk;
+impl AsyncWrite for MeteredStream<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
     }
 
-    if !key.is_empty() {
-        log::info!("Key: {}", key);
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
     }
 
-    key
-}
\ No newline at end of file
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn set_param_names() {
+        for name in ["max-pending", "MAX_PENDING", "MAX-PENDING"] {
+            set_param(name, "7").await.unwrap();
+            assert_eq!(MAX_PENDING.load(Ordering::SeqCst), 7);
+            MAX_PENDING.store(0, Ordering::SeqCst);
+        }
+        let port_forward = ConnType::PORT_FORWARD.value();
+        for name in ["policy-port_forward", "POLICY-PORT-FORWARD", "POLICY_PORT_FORWARD"] {
+            CONN_POLICIES.write().await.clear();
+            set_param(name, "deny").await.unwrap();
+            assert!(!CONN_POLICIES.read().await[&port_forward].allow);
+        }
+        assert!(set_param("policy-nothing", "deny").await.is_err());
+    }
+}
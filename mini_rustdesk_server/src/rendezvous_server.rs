@@ -1,40 +1,59 @@
 use crate::ResultType;
 use std::{
     collections::HashMap,
-    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    net::SocketAddr,
+    sync::atomic::{AtomicUsize, Ordering},
     sync::Arc,
     time::Instant,
 };
-use ipnetwork::Ipv4Network;
+use protobuf::Message as _;
 use sodiumoxide::crypto::sign;
-use crate::database;
+use crate::common::{get_arg, get_server_sk, normalize_relay_addr};
+use crate::rendezvous::*;
+use crate::tcp::{listen_any, FramedStream};
 
 use tokio::{
     self,
-    io::{AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream},
-    sync::{mpsc, Mutex},
-    time::{interval, Duration},
+    sync::{Mutex, RwLock},
 };
 
 #[derive(Clone)]
 struct Inner {
-    serial: i32,
     version: String,
-    software_url: String,
-    mask: Option<Ipv4Network>,
-    local_ip: String,
+    key: String,
     sk: Option<sign::SecretKey>,
 }
 
 type RelayServers = Vec<String>;
 
+/// The last load a relay reported with `RelayHeartbeat`.
+struct RelayStatus {
+    last_seen: Instant,
+    load: RelayLoad,
+}
+
+impl RelayStatus {
+    fn is_available(&self) -> bool {
+        if self.last_seen.elapsed().as_secs() > RELAY_HEARTBEAT_TIMEOUT {
+            return false;
+        }
+        let load = &self.load;
+        if load.max_sessions > 0 && load.sessions >= load.max_sessions {
+            return false;
+        }
+        !(load.capacity > 0 && load.bandwidth * 100 >= load.capacity * RELAY_OVERLOAD_PERCENT)
+    }
+}
+
+static ROTATION_RELAY_SERVER: AtomicUsize = AtomicUsize::new(0);
+const RELAY_HEARTBEAT_TIMEOUT: u64 = 30; // in seconds
+const RELAY_OVERLOAD_PERCENT: u64 = 90;
+
 #[derive(Clone)]
 pub struct RendezvousServer {
-    relay_servers: Arc<RelayServers>,
-    relay_servers0: Arc<RelayServers>,
-    rendezvous_servers: Arc<Vec<String>>,
+    relay_servers: Arc<RwLock<RelayServers>>,
+    relay_status: Arc<Mutex<HashMap<String, RelayStatus>>>,
     inner: Arc<Inner>,
 }
 
@@ -42,7 +61,220 @@ pub struct RendezvousServer {
 impl RendezvousServer {
     #[tokio::main(flavor = "multi_thread")]
     pub async fn start(port: i32, key: &str) -> ResultType<()> {
+        let (key, sk) = get_server_sk(key);
+        crate::tls::init().await?;
+        crate::proxy_protocol::init();
+        let relay_servers = get_servers(&get_arg("relay-servers"), "relay-servers");
+        let rs = Self {
+            relay_servers: Arc::new(RwLock::new(relay_servers)),
+            relay_status: Default::default(),
+            inner: Arc::new(Inner {
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+                key,
+                sk,
+            }),
+        };
+        log::info!("Listening on tcp :{}", port);
+        let listener = listen_any(port as _, true).await?;
+        rs.check_ipc();
+        let main_task = rs.io_loop(listener);
+        let listen_signal = crate::common::listen_signal();
+        tokio::select!(
+            res = main_task => res,
+            res = listen_signal => res,
+        )
+    }
+
+    async fn io_loop(&self, listener: TcpListener) -> ResultType<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            stream.set_nodelay(true).ok();
+            let rs = self.clone();
+            tokio::spawn(async move {
+                allow_err!(rs.handle_tcp(stream, addr).await);
+            });
+        }
+    }
+
+    /// Serves relays reporting their load with `RelayHeartbeat` and clients
+    /// asking with `RequestRelay` which relay to use. Anything else closes
+    /// the connection.
+    async fn handle_tcp(&self, stream: TcpStream, addr: SocketAddr) -> ResultType<()> {
+        let mut stream = stream;
+        let addr = crate::proxy_protocol::accept(&mut stream, addr).await?;
+        let stream = crate::tls::accept(stream).await?;
+        let mut stream = FramedStream::from(stream, addr);
+        while let Some(Ok(bytes)) = stream.next_timeout(RELAY_HEARTBEAT_TIMEOUT * 1000).await {
+            match RendezvousMessage::parse_from_bytes(&bytes).map(|x| x.union) {
+                Ok(Some(rendezvous_message::Union::RelayHeartbeat(hb))) => {
+                    self.handle_relay_heartbeat(hb, addr).await;
+                }
+                Ok(Some(rendezvous_message::Union::RequestRelay(rf))) => {
+                    let mut msg_out = RendezvousMessage::new();
+                    msg_out.set_relay_response(self.handle_request_relay(rf, addr).await);
+                    stream.send(&msg_out).await?;
+                }
+                _ => break,
+            }
+        }
         Ok(())
     }
 
+    /// Assigns a relay to the session `rf.uuid`, unless the client already
+    /// picked one.
+    async fn handle_request_relay(&self, rf: RequestRelay, addr: SocketAddr) -> RelayResponse {
+        let mut rr = RelayResponse {
+            uuid: rf.uuid,
+            version: self.inner.version.clone(),
+            ..Default::default()
+        };
+        if !self.inner.key.is_empty() && rf.licence_key != self.inner.key {
+            log::warn!("Relay request from {} with wrong key", addr);
+            rr.refuse_reason = "Key mismatch".to_owned();
+            return rr;
+        }
+        rr.relay_server = if rf.relay_server.is_empty() {
+            self.get_relay_server().await
+        } else {
+            rf.relay_server
+        };
+        if rr.relay_server.is_empty() {
+            rr.refuse_reason = "No relay server available".to_owned();
+        }
+        log::debug!("Relay {} assigned to {} for {}", rr.relay_server, addr, rr.uuid);
+        rr
+    }
+
+    async fn handle_relay_heartbeat(&self, hb: RelayHeartbeat, addr: SocketAddr) {
+        let pk = match self.inner.sk.as_ref() {
+            Some(sk) => sign::PublicKey::from_slice(&sk.0[sign::SECRETKEYBYTES / 2..]),
+            None => None,
+        };
+        let load = pk
+            .and_then(|pk| sign::verify(&hb.signed_load, &pk).ok())
+            .and_then(|x| RelayLoad::parse_from_bytes(&x).ok());
+        let load = match load {
+            Some(load) => load,
+            None => {
+                log::warn!("Invalid relay heartbeat from {}", addr);
+                return;
+            }
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_secs() as i64)
+            .unwrap_or(0);
+        if now.abs_diff(load.time) > RELAY_HEARTBEAT_TIMEOUT {
+            log::warn!("Stale relay heartbeat from {}", addr);
+            return;
+        }
+        let name = if load.relay_server.is_empty() {
+            crate::common::try_into_v4(addr).ip().to_string()
+        } else {
+            load.relay_server.clone()
+        };
+        log::debug!("Relay {} load: {:?}", name, load);
+        self.relay_status.lock().await.insert(
+            normalize_relay_addr(&name),
+            RelayStatus {
+                last_seen: Instant::now(),
+                load,
+            },
+        );
+    }
+
+    /// Picks the relay server to fill into `RelayResponse`, round robin
+    /// over the relays that are neither dead nor overloaded. Relays that
+    /// never sent a heartbeat are assumed to be available.
+    async fn get_relay_server(&self) -> String {
+        let relay_servers = self.relay_servers.read().await;
+        if relay_servers.is_empty() {
+            return "".to_owned();
+        }
+        let n = relay_servers.len();
+        let start = ROTATION_RELAY_SERVER.fetch_add(1, Ordering::SeqCst);
+        let status = self.relay_status.lock().await;
+        for i in 0..n {
+            let server = &relay_servers[(start + i) % n];
+            match status.get(&normalize_relay_addr(server)) {
+                Some(s) if !s.is_available() => {}
+                _ => return server.clone(),
+            }
+        }
+        // all of them look dead or overloaded, one may still work
+        relay_servers[start % n].clone()
+    }
+
+    #[cfg(unix)]
+    async fn status(&self) -> serde_json::Value {
+        let relays: serde_json::Map<String, serde_json::Value> = self
+            .relay_status
+            .lock()
+            .await
+            .iter()
+            .map(|(name, s)| {
+                let status = serde_json::json!({
+                    "available": s.is_available(),
+                    "last_seen": s.last_seen.elapsed().as_secs(),
+                    "sessions": s.load.sessions,
+                    "max_sessions": s.load.max_sessions,
+                    "bandwidth": s.load.bandwidth,
+                    "capacity": s.load.capacity,
+                });
+                (name.clone(), status)
+            })
+            .collect();
+        serde_json::json!({
+            "relay_servers": *self.relay_servers.read().await,
+            "relays": relays,
+        })
+    }
+
+    #[cfg(unix)]
+    async fn handle_ipc(&self, cmd: crate::ipc::Command) -> crate::ipc::Response {
+        use crate::ipc::{Command, Response};
+        match cmd {
+            Command::Status => Response::ok(self.status().await),
+            Command::Kick { .. } => Response::err("Sessions are kicked on the relay server"),
+            Command::Config { name, value } => {
+                // the relays are the only setting that is not read per request
+                if crate::common::arg_name(&name) != "RELAY-SERVERS" {
+                    return Response::err(format!("Unknown config {}", name));
+                }
+                *self.relay_servers.write().await = get_servers(&value, "relay-servers");
+                Response::ok(serde_json::Value::Null)
+            }
+        }
+    }
+
+    /// Serves local control commands on the unix socket `ipc-path`, if set.
+    fn check_ipc(&self) {
+        #[cfg(unix)]
+        {
+            let path = get_arg("ipc-path");
+            if path.is_empty() {
+                return;
+            }
+            let rs = self.clone();
+            tokio::spawn(async move {
+                let handler = move |cmd| {
+                    let rs = rs.clone();
+                    async move { rs.handle_ipc(cmd).await }
+                };
+                if let Err(err) = crate::ipc::serve(&path, handler).await {
+                    log::error!("Ipc listener on {} failed: {}", path, err);
+                }
+            });
+        }
+    }
+}
+
+fn get_servers(s: &str, tag: &str) -> Vec<String> {
+    let servers: Vec<String> = s
+        .split(',')
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .collect();
+    log::info!("{}={:?}", tag, servers);
+    servers
 }
\ No newline at end of file
@@ -0,0 +1,533 @@
+//! Reliable, ordered message stream over a udp flow, e.g. after a successful
+//! `punch::punch_udp`. The design follows KCP: messages are split into
+//! segments with sequence numbers, each segment is acknowledged on its own
+//! and all before `una` cumulatively, lost segments are resent on timeout or
+//! after three later segments were acknowledged, and the send window follows
+//! TCP-like slow start and congestion avoidance. Sequence numbers wrap around
+//! and are only ever compared with `seq_lt`.
+
+use crate::common::{timeout, try_into_v4};
+use crate::udp::FramedSocket;
+use crate::ResultType;
+use anyhow::{anyhow, bail};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use protobuf::Message;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Error, ErrorKind},
+    net::SocketAddr,
+};
+use tokio::{
+    sync::mpsc,
+    time::{interval, Duration, Instant},
+};
+use tokio_socks::TargetAddr;
+
+const CMD_DATA: u8 = 1;
+const CMD_ACK: u8 = 2;
+// conv u32, cmd u8, frg u16, wnd u16, sn u32, una u32, data segments then
+// carry their length as u16 and the payload
+const HEADER_LEN: usize = 17;
+const MTU: usize = 1400;
+const MSS: usize = 1200;
+const TICK: u64 = 10; // in ms
+const MIN_RTO: u64 = 100; // in ms
+const MAX_RTO: u64 = 60_000; // in ms
+const FASTACK_THRESHOLD: u32 = 3;
+// transmissions of one segment before the peer is considered gone
+const DEAD_LINK: u32 = 20;
+const SND_WND: u32 = 1024; // in segments
+const RCV_WND: u32 = 1024; // in segments
+const SND_QUEUE_MAX: usize = 4096; // in segments
+// segments of one message, so that a whole message always fits into the
+// send queue and the receiver's window
+const MAX_FRAGMENTS: usize = 1024;
+pub const MAX_MESSAGE_LEN: usize = MAX_FRAGMENTS * MSS;
+const CHANNEL_SIZE: usize = 64; // in messages
+
+/// A reliable message stream to `peer` with the same send/next interface as
+/// `FramedStream`. Both sides must use the same `conv`, packets of other
+/// conversations are ignored.
+pub struct RudpStream {
+    tx: mpsc::Sender<Vec<u8>>,
+    rx: mpsc::Receiver<Result<BytesMut, Error>>,
+    peer: SocketAddr,
+}
+
+impl RudpStream {
+    pub fn new(socket: FramedSocket, peer: SocketAddr, conv: u32) -> Self {
+        Self::with_sn(socket, peer, conv, 0)
+    }
+
+    fn with_sn(socket: FramedSocket, peer: SocketAddr, conv: u32, sn: u32) -> Self {
+        let (tx, user_rx) = mpsc::channel(CHANNEL_SIZE);
+        let (user_tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        let control = Control::new(socket, peer, conv, sn, user_tx);
+        tokio::spawn(control.run(user_rx));
+        Self { tx, rx, peer }
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    #[inline]
+    pub async fn send(&mut self, msg: &impl Message) -> ResultType<()> {
+        self.send_raw(msg.write_to_bytes()?).await
+    }
+
+    /// Waits while the send queue is full. Messages over `MAX_MESSAGE_LEN`
+    /// are refused.
+    #[inline]
+    pub async fn send_raw(&mut self, msg: Vec<u8>) -> ResultType<()> {
+        if msg.len() > MAX_MESSAGE_LEN {
+            bail!("Message of {} bytes too large for reliable udp", msg.len());
+        }
+        self.tx
+            .send(msg)
+            .await
+            .map_err(|_| anyhow!("Reliable udp stream closed"))
+    }
+
+    #[inline]
+    pub async fn next(&mut self) -> Option<Result<BytesMut, Error>> {
+        self.rx.recv().await
+    }
+
+    #[inline]
+    pub async fn next_timeout(&mut self, ms: u64) -> Option<Result<BytesMut, Error>> {
+        timeout(ms, self.next()).await.unwrap_or(None)
+    }
+}
+
+/// Whether `a` comes before `b`, for sequence numbers less than 2^31 apart.
+#[inline]
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+struct Segment {
+    sn: u32,
+    frg: u16,
+    data: Bytes,
+    sent_at: Instant,
+    resend_at: Instant,
+    rto: u64,
+    xmit: u32,
+    fastack: u32,
+}
+
+struct Control {
+    conv: u32,
+    socket: FramedSocket,
+    peer: SocketAddr,
+    out: mpsc::Sender<Result<BytesMut, Error>>,
+    snd_nxt: u32,
+    snd_una: u32,
+    snd_queue: VecDeque<Segment>,
+    // sent and not yet acknowledged, in sequence
+    snd_buf: VecDeque<Segment>,
+    rcv_nxt: u32,
+    rcv_buf: HashMap<u32, (u16, Bytes)>,
+    partial: BytesMut,
+    rcv_queue: VecDeque<BytesMut>,
+    acks: Vec<u32>,
+    cwnd: u32,
+    ssthresh: u32,
+    incr: u32,
+    rmt_wnd: u32,
+    srtt: u64,
+    rttvar: u64,
+    rto: u64,
+}
+
+impl Control {
+    fn new(
+        socket: FramedSocket,
+        peer: SocketAddr,
+        conv: u32,
+        sn: u32,
+        out: mpsc::Sender<Result<BytesMut, Error>>,
+    ) -> Self {
+        Self {
+            conv,
+            socket,
+            peer,
+            out,
+            snd_nxt: sn,
+            snd_una: sn,
+            snd_queue: Default::default(),
+            snd_buf: Default::default(),
+            rcv_nxt: sn,
+            rcv_buf: Default::default(),
+            partial: BytesMut::new(),
+            rcv_queue: Default::default(),
+            acks: Vec::new(),
+            cwnd: 1,
+            ssthresh: 2,
+            incr: 0,
+            rmt_wnd: RCV_WND,
+            srtt: 0,
+            rttvar: 0,
+            rto: MIN_RTO * 2,
+        }
+    }
+
+    async fn run(mut self, mut user_rx: mpsc::Receiver<Vec<u8>>) {
+        let mut timer = interval(Duration::from_millis(TICK));
+        let mut closing = false;
+        loop {
+            tokio::select! {
+                msg = user_rx.recv(),
+                    if !closing && self.snd_queue.len() + MAX_FRAGMENTS <= SND_QUEUE_MAX =>
+                {
+                    match msg {
+                        Some(msg) => self.enqueue(msg),
+                        None => closing = true,
+                    }
+                }
+                res = self.socket.next() => match res {
+                    Some(Ok((bytes, TargetAddr::Ip(addr))))
+                        if try_into_v4(addr) == try_into_v4(self.peer) =>
+                    {
+                        self.input(bytes.freeze());
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => {
+                        self.fail(ErrorKind::Other, err.to_string()).await;
+                        return;
+                    }
+                    None => return,
+                },
+                _ = timer.tick() => {}
+            }
+            self.deliver();
+            if let Err(err) = self.flush().await {
+                self.fail(ErrorKind::TimedOut, err.to_string()).await;
+                return;
+            }
+            if closing && self.snd_queue.is_empty() && self.snd_buf.is_empty() {
+                return;
+            }
+        }
+    }
+
+    async fn fail(&mut self, kind: ErrorKind, err: String) {
+        log::debug!("Reliable udp stream to {} failed: {}", self.peer, err);
+        self.out.send(Err(Error::new(kind, err))).await.ok();
+    }
+
+    fn enqueue(&mut self, msg: Vec<u8>) {
+        let msg = Bytes::from(msg);
+        let count = msg.len().div_ceil(MSS).max(1);
+        let now = Instant::now();
+        for i in 0..count {
+            let end = ((i + 1) * MSS).min(msg.len());
+            self.snd_queue.push_back(Segment {
+                sn: 0,
+                frg: (count - i - 1) as u16,
+                data: msg.slice(i * MSS..end),
+                sent_at: now,
+                resend_at: now,
+                rto: 0,
+                xmit: 0,
+                fastack: 0,
+            });
+        }
+    }
+
+    fn input(&mut self, mut bytes: Bytes) {
+        while bytes.len() >= HEADER_LEN {
+            let conv = bytes.get_u32_le();
+            let cmd = bytes.get_u8();
+            let frg = bytes.get_u16_le();
+            let wnd = bytes.get_u16_le();
+            let sn = bytes.get_u32_le();
+            let una = bytes.get_u32_le();
+            if conv != self.conv {
+                return;
+            }
+            self.rmt_wnd = wnd as u32;
+            self.ack_until(una);
+            match cmd {
+                CMD_ACK => self.ack(sn),
+                CMD_DATA => {
+                    if bytes.len() < 2 {
+                        return;
+                    }
+                    let len = bytes.get_u16_le() as usize;
+                    if bytes.len() < len {
+                        return;
+                    }
+                    let data = bytes.split_to(len);
+                    if frg as usize >= MAX_FRAGMENTS {
+                        continue;
+                    }
+                    if seq_lt(sn, self.rcv_nxt.wrapping_add(RCV_WND)) {
+                        self.acks.push(sn);
+                        if !seq_lt(sn, self.rcv_nxt) {
+                            self.rcv_buf.entry(sn).or_insert((frg, data));
+                        }
+                    }
+                }
+                _ => return,
+            }
+        }
+        while let Some((frg, data)) = self.rcv_buf.remove(&self.rcv_nxt) {
+            self.partial.extend_from_slice(&data);
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            if frg == 0 {
+                let msg = self.partial.split();
+                self.rcv_queue.push_back(msg);
+            }
+        }
+    }
+
+    fn ack_until(&mut self, una: u32) {
+        // nothing beyond what we sent can be acknowledged
+        if seq_lt(self.snd_nxt, una) {
+            return;
+        }
+        while let Some(seg) = self.snd_buf.front() {
+            if !seq_lt(seg.sn, una) {
+                break;
+            }
+            self.snd_buf.pop_front();
+            self.grow_cwnd();
+        }
+        self.update_una();
+    }
+
+    fn ack(&mut self, sn: u32) {
+        let seg = self
+            .snd_buf
+            .iter()
+            .position(|x| x.sn == sn)
+            .and_then(|i| self.snd_buf.remove(i));
+        if let Some(seg) = seg {
+            // Karn: only segments sent once give a usable sample
+            if seg.xmit == 1 {
+                self.update_rtt(seg.sent_at.elapsed().as_millis() as u64);
+            }
+            self.grow_cwnd();
+        }
+        for seg in self.snd_buf.iter_mut().take_while(|x| seq_lt(x.sn, sn)) {
+            seg.fastack += 1;
+        }
+        self.update_una();
+    }
+
+    fn update_una(&mut self) {
+        self.snd_una = self.snd_buf.front().map(|x| x.sn).unwrap_or(self.snd_nxt);
+    }
+
+    fn update_rtt(&mut self, rtt: u64) {
+        if self.srtt == 0 {
+            self.srtt = rtt.max(1);
+            self.rttvar = rtt / 2;
+        } else {
+            let delta = rtt.abs_diff(self.srtt);
+            self.rttvar = (3 * self.rttvar + delta) / 4;
+            self.srtt = ((7 * self.srtt + rtt) / 8).max(1);
+        }
+        self.rto = (self.srtt + (4 * self.rttvar).max(TICK)).clamp(MIN_RTO, MAX_RTO);
+    }
+
+    fn grow_cwnd(&mut self) {
+        if self.cwnd < self.ssthresh {
+            self.cwnd += 1;
+        } else {
+            self.incr += 1;
+            if self.incr >= self.cwnd {
+                self.cwnd += 1;
+                self.incr = 0;
+            }
+        }
+        self.cwnd = self.cwnd.min(SND_WND);
+    }
+
+    fn deliver(&mut self) {
+        while let Some(msg) = self.rcv_queue.pop_front() {
+            match self.out.try_send(Ok(msg)) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(Ok(msg))) => {
+                    self.rcv_queue.push_front(msg);
+                    return;
+                }
+                Err(_) => return,
+            }
+        }
+    }
+
+    fn write_header(&self, buf: &mut BytesMut, cmd: u8, frg: u16, sn: u32) {
+        let used = self.rcv_buf.len() + self.rcv_queue.len();
+        let wnd = (RCV_WND as usize).saturating_sub(used) as u16;
+        buf.put_u32_le(self.conv);
+        buf.put_u8(cmd);
+        buf.put_u16_le(frg);
+        buf.put_u16_le(wnd);
+        buf.put_u32_le(sn);
+        buf.put_u32_le(self.rcv_nxt);
+    }
+
+    async fn send_packet(&mut self, buf: &mut BytesMut, needed: usize) -> ResultType<()> {
+        if !buf.is_empty() && buf.len() + needed > MTU {
+            let peer = self.peer;
+            self.socket.send_bytes(buf.split().freeze(), peer).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> ResultType<()> {
+        let mut buf = BytesMut::with_capacity(MTU);
+        for sn in std::mem::take(&mut self.acks) {
+            self.send_packet(&mut buf, HEADER_LEN).await?;
+            self.write_header(&mut buf, CMD_ACK, 0, sn);
+        }
+        // a zero window still lets one segment through, as a probe
+        let wnd = self.cwnd.min(self.rmt_wnd).max(1);
+        while self.snd_nxt.wrapping_sub(self.snd_una) < wnd {
+            match self.snd_queue.pop_front() {
+                Some(mut seg) => {
+                    seg.sn = self.snd_nxt;
+                    self.snd_nxt = self.snd_nxt.wrapping_add(1);
+                    self.snd_buf.push_back(seg);
+                }
+                None => break,
+            }
+        }
+        let now = Instant::now();
+        let (mut lost, mut fast) = (false, false);
+        let mut due = Vec::new();
+        for seg in self.snd_buf.iter_mut() {
+            let resend = if seg.xmit == 0 {
+                seg.rto = self.rto;
+                true
+            } else if now >= seg.resend_at {
+                seg.rto = (seg.rto * 2).min(MAX_RTO);
+                lost = true;
+                true
+            } else if seg.fastack >= FASTACK_THRESHOLD {
+                fast = true;
+                true
+            } else {
+                false
+            };
+            if resend {
+                seg.xmit += 1;
+                if seg.xmit > DEAD_LINK {
+                    return Err(anyhow!("Peer did not acknowledge segment {}", seg.sn));
+                }
+                seg.fastack = 0;
+                seg.sent_at = now;
+                seg.resend_at = now + Duration::from_millis(seg.rto);
+                due.push((seg.sn, seg.frg, seg.data.clone()));
+            }
+        }
+        for (sn, frg, data) in due {
+            self.send_packet(&mut buf, HEADER_LEN + 2 + data.len()).await?;
+            self.write_header(&mut buf, CMD_DATA, frg, sn);
+            buf.put_u16_le(data.len() as u16);
+            buf.put_slice(&data);
+        }
+        if fast {
+            let inflight = self.snd_nxt.wrapping_sub(self.snd_una);
+            self.ssthresh = (inflight / 2).max(2);
+            self.cwnd = self.ssthresh + FASTACK_THRESHOLD;
+            self.incr = 0;
+        }
+        if lost {
+            self.ssthresh = (self.cwnd / 2).max(2);
+            self.cwnd = 1;
+            self.incr = 0;
+        }
+        if !buf.is_empty() {
+            let peer = self.peer;
+            self.socket.send_bytes(buf.freeze(), peer).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    /// Forwards packets between `a` and `b`, dropping every `n`th one.
+    async fn lossy_relay(a: SocketAddr, b: SocketAddr, n: usize) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65536];
+            let mut count = 0;
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                count += 1;
+                if count % n == 0 {
+                    continue;
+                }
+                let to = if from == a { b } else { a };
+                socket.send_to(&buf[..len], to).await.ok();
+            }
+        });
+        addr
+    }
+
+    async fn pair(sn: u32) -> (RudpStream, RudpStream) {
+        let a = FramedSocket::new("127.0.0.1:0").await.unwrap();
+        let b = FramedSocket::new("127.0.0.1:0").await.unwrap();
+        let relay = lossy_relay(a.local_addr().unwrap(), b.local_addr().unwrap(), 10).await;
+        (
+            RudpStream::with_sn(a, relay, 7, sn),
+            RudpStream::with_sn(b, relay, 7, sn),
+        )
+    }
+
+    fn message(i: usize) -> Vec<u8> {
+        let len = [i, MSS, 3 * MSS + 17, 10_000 + i][i % 4];
+        (0..len).map(|x| (x * 31 + i) as u8).collect()
+    }
+
+    async fn round_trip(sn: u32) {
+        let (mut a, mut b) = pair(sn).await;
+        let sender = tokio::spawn(async move {
+            for i in 0..100 {
+                a.send_raw(message(i)).await.unwrap();
+            }
+            a
+        });
+        for i in 0..100 {
+            let msg = b.next_timeout(10_000).await.unwrap().unwrap();
+            assert_eq!(&msg[..], &message(i)[..], "message {}", i);
+        }
+        let mut a = sender.await.unwrap();
+        b.send_raw(b"pong".to_vec()).await.unwrap();
+        let msg = a.next_timeout(10_000).await.unwrap().unwrap();
+        assert_eq!(&msg[..], b"pong");
+    }
+
+    #[tokio::test]
+    async fn lossy_loopback() {
+        round_trip(0).await;
+    }
+
+    #[tokio::test]
+    async fn lossy_loopback_across_wraparound() {
+        round_trip(u32::MAX - 100).await;
+    }
+
+    #[tokio::test]
+    async fn too_large_message() {
+        let (mut a, _b) = pair(0).await;
+        assert!(a.send_raw(vec![0; MAX_MESSAGE_LEN + 1]).await.is_err());
+        assert!(a.send_raw(vec![0; MAX_MESSAGE_LEN]).await.is_ok());
+    }
+
+    #[test]
+    fn seq_wraps() {
+        assert!(seq_lt(1, 2));
+        assert!(!seq_lt(2, 2));
+        assert!(seq_lt(u32::MAX, 0));
+        assert!(!seq_lt(0, u32::MAX));
+    }
+}
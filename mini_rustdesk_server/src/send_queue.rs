@@ -0,0 +1,218 @@
+//! Send queue with priority classes for connections that carry mixed
+//! traffic. Producers push through a cloneable `PrioritySender`, the task
+//! owning the `FramedStream` drains `PriorityQueue::next` and always takes
+//! the highest class that has something pending, so a file transfer only
+//! uses the link while nothing more urgent waits.
+
+use crate::message::{file_response, message, Message};
+use crate::tcp::FramedStream;
+use crate::ResultType;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use protobuf::Message as _;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+// in messages, small for the large ones so that a full queue is not a lot of
+// latency in itself
+const CONTROL_CAPACITY: usize = 256;
+const AUDIO_CAPACITY: usize = 64;
+const VIDEO_CAPACITY: usize = 16;
+const BULK_CAPACITY: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Input events, chat, file transfer control and everything else small.
+    Control,
+    Audio,
+    Video,
+    /// File blocks.
+    Bulk,
+}
+
+impl Priority {
+    pub fn of(msg: &Message) -> Self {
+        match &msg.union {
+            Some(message::Union::AudioFrame(_)) => Self::Audio,
+            Some(message::Union::VideoFrame(_)) => Self::Video,
+            Some(message::Union::FileResponse(res)) => match res.union {
+                Some(file_response::Union::Block(_)) => Self::Bulk,
+                _ => Self::Control,
+            },
+            _ => Self::Control,
+        }
+    }
+}
+
+/// A serialized message waiting to be written.
+pub struct Queued {
+    pub bytes: Vec<u8>,
+    /// The transfer job a file block belongs to, so that the task writing it
+    /// can report how long the write took, see `TransferJob::on_block_sent`.
+    pub job: Option<i32>,
+}
+
+#[derive(Clone)]
+pub struct PrioritySender {
+    control: mpsc::Sender<Queued>,
+    audio: mpsc::Sender<Queued>,
+    video: mpsc::Sender<Queued>,
+    bulk: mpsc::Sender<Queued>,
+}
+
+pub struct PriorityQueue {
+    control: mpsc::Receiver<Queued>,
+    audio: mpsc::Receiver<Queued>,
+    video: mpsc::Receiver<Queued>,
+    bulk: mpsc::Receiver<Queued>,
+}
+
+pub fn new() -> (PrioritySender, PriorityQueue) {
+    let (control, control_rx) = mpsc::channel(CONTROL_CAPACITY);
+    let (audio, audio_rx) = mpsc::channel(AUDIO_CAPACITY);
+    let (video, video_rx) = mpsc::channel(VIDEO_CAPACITY);
+    let (bulk, bulk_rx) = mpsc::channel(BULK_CAPACITY);
+    (
+        PrioritySender {
+            control,
+            audio,
+            video,
+            bulk,
+        },
+        PriorityQueue {
+            control: control_rx,
+            audio: audio_rx,
+            video: video_rx,
+            bulk: bulk_rx,
+        },
+    )
+}
+
+impl PrioritySender {
+    fn get(&self, priority: Priority) -> &mpsc::Sender<Queued> {
+        match priority {
+            Priority::Control => &self.control,
+            Priority::Audio => &self.audio,
+            Priority::Video => &self.video,
+            Priority::Bulk => &self.bulk,
+        }
+    }
+
+    /// Queues `msg` in the class `Priority::of` gives it, waiting while that
+    /// class is full.
+    #[inline]
+    pub async fn send(&self, msg: &Message) -> ResultType<()> {
+        self.send_raw(msg.write_to_bytes()?, Priority::of(msg)).await
+    }
+
+    #[inline]
+    pub async fn send_raw(&self, msg: Vec<u8>, priority: Priority) -> ResultType<()> {
+        self.queue(Queued { bytes: msg, job: None }, priority).await
+    }
+
+    /// Queues a file block of transfer job `job` in the bulk class.
+    pub async fn send_block(&self, msg: &Message, job: i32) -> ResultType<()> {
+        let queued = Queued {
+            bytes: msg.write_to_bytes()?,
+            job: Some(job),
+        };
+        self.queue(queued, Priority::Bulk).await
+    }
+
+    async fn queue(&self, queued: Queued, priority: Priority) -> ResultType<()> {
+        self.get(priority)
+            .send(queued)
+            .await
+            .map_err(|_| anyhow!("Send queue closed"))
+    }
+
+    /// Whether a message of `priority` can be queued without waiting. Lets
+    /// producers of bulk data skip producing it instead of blocking, e.g.
+    /// not read the next file block yet.
+    pub fn has_capacity(&self, priority: Priority) -> bool {
+        self.get(priority).capacity() > 0
+    }
+
+    /// Waits until a message of `priority` can be queued.
+    pub async fn ready(&self, priority: Priority) -> ResultType<()> {
+        self.get(priority)
+            .reserve()
+            .await
+            .map(|_| ())
+            .map_err(|_| anyhow!("Send queue closed"))
+    }
+}
+
+impl PriorityQueue {
+    /// The next message to write, the highest class first. Returns `None`
+    /// once all senders are dropped and everything was taken. Enqueuing
+    /// takes no time, so the caller times the write of a file block and
+    /// reports it to its job.
+    pub async fn next(&mut self) -> Option<Queued> {
+        tokio::select! {
+            biased;
+            Some(msg) = self.control.recv() => Some(msg),
+            Some(msg) = self.audio.recv() => Some(msg),
+            Some(msg) = self.video.recv() => Some(msg),
+            Some(msg) = self.bulk.recv() => Some(msg),
+            else => None,
+        }
+    }
+
+    /// Writes everything queued to `stream` until all senders are dropped.
+    /// The stream is borrowed all along, so this only suits connections
+    /// that do not read meanwhile; others select on `next` in their read
+    /// loop.
+    pub async fn run(mut self, stream: &mut FramedStream) -> ResultType<()> {
+        while let Some(msg) = self.next().await {
+            stream.send_raw(msg.bytes).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the file transfer code writes its messages, either straight to a
+/// stream or into a send queue.
+#[async_trait]
+pub trait MessageSink: Send {
+    async fn send_message(&mut self, msg: &Message) -> ResultType<()>;
+
+    /// Sends a file block of transfer job `job`. Returns how long the write
+    /// took, or `None` if the block was only queued and is timed where the
+    /// queue is drained.
+    async fn send_block(&mut self, msg: &Message, job: i32) -> ResultType<Option<Duration>>;
+
+    /// Whether a file block can be sent now without waiting.
+    fn can_send_bulk(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl MessageSink for FramedStream {
+    async fn send_message(&mut self, msg: &Message) -> ResultType<()> {
+        self.send(msg).await
+    }
+
+    async fn send_block(&mut self, msg: &Message, _job: i32) -> ResultType<Option<Duration>> {
+        let start = Instant::now();
+        self.send(msg).await?;
+        Ok(Some(start.elapsed()))
+    }
+}
+
+#[async_trait]
+impl MessageSink for PrioritySender {
+    async fn send_message(&mut self, msg: &Message) -> ResultType<()> {
+        self.send(msg).await
+    }
+
+    async fn send_block(&mut self, msg: &Message, job: i32) -> ResultType<Option<Duration>> {
+        PrioritySender::send_block(self, msg, job).await?;
+        Ok(None)
+    }
+
+    fn can_send_bulk(&self) -> bool {
+        self.has_capacity(Priority::Bulk)
+    }
+}
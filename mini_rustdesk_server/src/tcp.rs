@@ -1,11 +1,16 @@
 use crate::{ bytes_codec::BytesCodec, ResultType};
 use anyhow::Context as AnyhowCtx;
-use bytes::{BufMut, Bytes, BytesMut};
-use futures::{SinkExt, StreamExt};
+use bytes::{Bytes, BytesMut};
+use futures::{stream::FuturesUnordered, SinkExt, StreamExt};
 use protobuf::Message;
-use sodiumoxide::crypto::secretbox::{self, Key, Nonce};
+use crate::encrypt::Encrypt;
+use crate::message::TestDelay;
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::proxy::{http_connect, Proxy, ProxyScheme};
+use sodiumoxide::crypto::secretbox::Key;
 use std::{
-    io::{self, Error, ErrorKind},
+    collections::VecDeque,
+    io::{self, Error},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     ops::{Deref, DerefMut},
     pin::Pin,
@@ -15,7 +20,7 @@ use crate::common::timeout;
 
 use tokio::{
     io::{AsyncRead, AsyncWrite, ReadBuf},
-    net::{lookup_host, TcpListener, TcpSocket, ToSocketAddrs},
+    net::{lookup_host, TcpListener, TcpSocket, TcpStream, ToSocketAddrs},
 };
 use tokio_socks::{tcp::Socks5Stream, IntoTargetAddr, ToProxyAddrs};
 use tokio_util::codec::Framed;
@@ -27,8 +32,9 @@ pub struct DynTcpStream(Box<dyn TcpStreamTrait + Send + Sync>);
 pub struct FramedStream(
     Framed<DynTcpStream, BytesCodec>,
     SocketAddr,
-    Option<(Key, u64, u64)>,
+    Option<Encrypt>,
     u64,
+    Metrics,
 );
 
 impl Deref for FramedStream {
@@ -59,44 +65,96 @@ impl DerefMut for DynTcpStream {
     }
 }
 
-fn new_socket(addr: std::net::SocketAddr, reuse: bool) -> Result<TcpSocket, std::io::Error> {
+pub fn new_socket(addr: std::net::SocketAddr, reuse: bool) -> Result<TcpSocket, std::io::Error> {
     let socket = match addr {
         std::net::SocketAddr::V4(..) => TcpSocket::new_v4()?,
         std::net::SocketAddr::V6(..) => TcpSocket::new_v6()?,
     };
     if reuse {
+        // allows connecting and listening from the same port, as hole
+        // punching needs
+        #[cfg(all(unix, not(target_os = "illumos")))]
+        socket.set_reuseport(true)?;
         socket.set_reuseaddr(true)?;
     }
     socket.bind(addr)?;
     Ok(socket)
 }
 
+/// Orders resolved addresses so that the address families alternate, starting
+/// with the family of the first address, as in RFC 8305.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().map(|x| x.is_ipv6()).unwrap_or(false);
+    let (mut first, mut second): (VecDeque<_>, VecDeque<_>) =
+        addrs.into_iter().partition(|x| x.is_ipv6() == first_v6);
+    let mut res = Vec::with_capacity(first.len() + second.len());
+    loop {
+        match (first.pop_front(), second.pop_front()) {
+            (None, None) => break,
+            (a, b) => res.extend(a.into_iter().chain(b)),
+        }
+    }
+    res
+}
+
+async fn connect_one(
+    remote_addr: SocketAddr,
+    local_addr: Option<SocketAddr>,
+) -> ResultType<TcpStream> {
+    let local = if let Some(addr) = local_addr {
+        addr
+    } else {
+        crate::config::get_any_listen_addr(remote_addr.is_ipv4())
+    };
+    Ok(new_socket(local, true)?.connect(remote_addr).await?)
+}
+
+/// Starts a connection attempt to the next address whenever the previous one
+/// fails or has not succeeded within `CONNECT_STAGGER`, and returns the first
+/// stream that connects. A broken address family thus costs a short delay
+/// instead of a full timeout.
+async fn race_connect(
+    addrs: Vec<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+) -> ResultType<TcpStream> {
+    let mut addrs = addrs.into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_err = anyhow::anyhow!("could not resolve to any address");
+    loop {
+        if let Some(addr) = addrs.next() {
+            attempts.push(connect_one(addr, local_addr));
+        } else if attempts.is_empty() {
+            return Err(last_err);
+        }
+        let has_more = addrs.len() > 0;
+        tokio::select! {
+            Some(res) = attempts.next() => match res {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = err,
+            },
+            _ = tokio::time::sleep(CONNECT_STAGGER), if has_more => {}
+            else => return Err(last_err),
+        }
+    }
+}
+
 impl FramedStream {
     pub async fn new<T: ToSocketAddrs + std::fmt::Display>(
         remote_addr: T,
         local_addr: Option<SocketAddr>,
         ms_timeout: u64,
     ) -> ResultType<Self> {
-        for remote_addr in lookup_host(&remote_addr).await? {
-            let local = if let Some(addr) = local_addr {
-                addr
-            } else {
-                crate::config::get_any_listen_addr(remote_addr.is_ipv4())
-            };
-            if let Ok(socket) = new_socket(local, true) {
-                if let Ok(Ok(stream)) =
-                    timeout(ms_timeout, socket.connect(remote_addr)).await
-                {
-                    stream.set_nodelay(true).ok();
-                    let addr = stream.local_addr()?;
-                    return Ok(Self(
-                        Framed::new(DynTcpStream(Box::new(stream)), BytesCodec::new()),
-                        addr,
-                        None,
-                        0,
-                    ));
-                }
-            }
+        let addrs = interleave_families(lookup_host(&remote_addr).await?.collect());
+        if let Ok(Ok(stream)) = timeout(ms_timeout, race_connect(addrs, local_addr)).await {
+            stream.set_nodelay(true).ok();
+            let addr = stream.local_addr()?;
+            return Ok(Self(
+                Framed::new(DynTcpStream(Box::new(stream)), BytesCodec::new()),
+                addr,
+                None,
+                0,
+                Metrics::default(),
+            ));
         }
         Err(anyhow::Error::msg(format!("Failed to connect to {remote_addr}")))
     }
@@ -143,6 +201,53 @@ impl FramedStream {
                 addr,
                 None,
                 0,
+                Metrics::default(),
+            ));
+        }
+        Err(anyhow::Error::msg("could not resolve to any address"))
+    }
+
+    /// Connects to `target` through the proxy given by `proxy_url`, see
+    /// `proxy::Proxy::from_url` for the accepted schemes.
+    pub async fn connect_url(
+        proxy_url: &str,
+        target: &str,
+        local_addr: Option<SocketAddr>,
+        ms_timeout: u64,
+    ) -> ResultType<Self> {
+        let proxy = Proxy::from_url(proxy_url)?;
+        if proxy.scheme == ProxyScheme::Socks5 {
+            return Self::connect(
+                proxy.addr.as_str(),
+                target,
+                local_addr,
+                &proxy.username,
+                &proxy.password,
+                ms_timeout,
+            )
+            .await;
+        }
+        if let Some(addr) = lookup_host(&proxy.addr).await?.next() {
+            let local = if let Some(addr) = local_addr {
+                addr
+            } else {
+                crate::config::get_any_listen_addr(addr.is_ipv4())
+            };
+            let mut stream =
+                timeout(ms_timeout, new_socket(local, true)?.connect(addr)).await??;
+            stream.set_nodelay(true).ok();
+            timeout(
+                ms_timeout,
+                http_connect(&mut stream, target, &proxy.username, &proxy.password),
+            )
+            .await??;
+            let addr = stream.local_addr()?;
+            return Ok(Self(
+                Framed::new(DynTcpStream(Box::new(stream)), BytesCodec::new()),
+                addr,
+                None,
+                0,
+                Metrics::default(),
             ));
         }
         Err(anyhow::Error::msg("could not resolve to any address"))
@@ -162,6 +267,7 @@ impl FramedStream {
             addr,
             None,
             0,
+            Metrics::default(),
         )
     }
 
@@ -170,6 +276,13 @@ impl FramedStream {
         self.2 = None;
     }
 
+    /// Takes the underlying stream out of the codec, together with the bytes
+    /// already read from it but not yet decoded.
+    pub fn into_raw(self) -> (DynTcpStream, BytesMut) {
+        let parts = self.0.into_parts();
+        (parts.io, parts.read_buf)
+    }
+
     pub fn is_secured(&self) -> bool {
         self.2.is_some()
     }
@@ -182,10 +295,14 @@ impl FramedStream {
     #[inline]
     pub async fn send_raw(&mut self, msg: Vec<u8>) -> ResultType<()> {
         let mut msg = msg;
-        if let Some(key) = self.2.as_mut() {
-            key.1 += 1;
-            let nonce = Self::get_nonce(key.1);
-            msg = secretbox::seal(&msg, &nonce, &key.0);
+        if let Some(encrypt) = self.2.as_mut() {
+            msg = match encrypt.seal(&msg) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    self.4.on_encryption_failure();
+                    return Err(Error::from(err).into());
+                }
+            };
         }
         self.send_bytes(bytes::Bytes::from(msg)).await?;
         Ok(())
@@ -193,56 +310,80 @@ impl FramedStream {
 
     #[inline]
     pub async fn send_bytes(&mut self, bytes: Bytes) -> ResultType<()> {
+        let len = bytes.len();
         if self.3 > 0 {
-            timeout(self.3, self.0.send(bytes)).await??;
+            let res = timeout(self.3, self.0.send(bytes)).await;
+            if res.is_err() {
+                self.4.on_send_timeout();
+            }
+            res??;
         } else {
             self.0.send(bytes).await?;
         }
+        self.4.on_sent(len);
         Ok(())
     }
 
     #[inline]
     pub async fn next(&mut self) -> Option<Result<BytesMut, Error>> {
-        let mut res = self.0.next().await;
-        if let Some(key) = self.2.as_mut() {
-            if let Some(Ok(bytes)) = res.as_mut() {
-                key.2 += 1;
-                let nonce = Self::get_nonce(key.2);
-                match secretbox::open(bytes, &nonce, &key.0) {
-                    Ok(res) => {
-                        bytes.clear();
-                        bytes.put_slice(&res);
-                    }
-                    Err(()) => {
-                        return Some(Err(Error::new(ErrorKind::Other, "decryption error")));
-                    }
+        if self.2.as_ref().map(|x| x.is_failed()) == Some(true) {
+            return None;
+        }
+        let res = self.0.next().await;
+        if let Some(Ok(bytes)) = res.as_ref() {
+            self.4.on_received(bytes.len());
+        }
+        match (self.2.as_mut(), res) {
+            (Some(encrypt), Some(Ok(bytes))) => match encrypt.open(&bytes) {
+                Ok(res) => Some(Ok(res[..].into())),
+                Err(err) => {
+                    self.4.on_encryption_failure();
+                    // the session can not be trusted anymore
+                    self.0.close().await.ok();
+                    Some(Err(err.into()))
                 }
-            }
+            },
+            (_, res) => res,
         }
-        res
     }
 
     #[inline]
     pub async fn next_timeout(&mut self, ms: u64) -> Option<Result<BytesMut, Error>> {
-        if let Ok(res) = timeout(ms, self.next()).await {
-            res
-        } else {
-            None
-        }
+        timeout(ms, self.next()).await.unwrap_or_default()
+    }
+
+    /// Feeds a `TestDelay` echoed back by the peer into the rtt estimate.
+    pub fn on_test_delay(&mut self, td: &TestDelay) {
+        self.4.on_test_delay(td);
+    }
+
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.4.snapshot()
     }
 
     pub fn set_key(&mut self, key: Key) {
-        self.2 = Some((key, 0, 0));
+        self.2 = Some(Encrypt::new(key));
+    }
+
+    /// Enables encryption with a key per direction derived from the session
+    /// `key`, see `Encrypt::with_direction_keys`. Only for peers that agreed
+    /// to it in the handshake.
+    pub fn set_direction_keys(&mut self, key: Key, initiator: bool) {
+        self.2 = Some(Encrypt::with_direction_keys(key, initiator));
     }
 
-    fn get_nonce(seqnum: u64) -> Nonce {
-        let mut nonce = Nonce([0u8; secretbox::NONCEBYTES]);
-        nonce.0[..std::mem::size_of_val(&seqnum)].copy_from_slice(&seqnum.to_le_bytes());
-        nonce
+    /// Enables in-band rekeying after `messages` messages or `interval`, see
+    /// `Encrypt::set_rekey`. Has no effect before a key is set, and only for
+    /// peers that agreed to it in the handshake.
+    pub fn set_rekey(&mut self, messages: u64, interval: std::time::Duration) {
+        if let Some(encrypt) = self.2.as_mut() {
+            encrypt.set_rekey(messages, interval);
+        }
     }
 }
 
 const DEFAULT_BACKLOG: u32 = 128;
+const CONNECT_STAGGER: std::time::Duration = std::time::Duration::from_millis(250);
 
 pub async fn new_listener<T: ToSocketAddrs>(addr: T, reuse: bool) -> ResultType<TcpListener> {
     if !reuse {
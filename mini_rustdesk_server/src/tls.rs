@@ -0,0 +1,167 @@
+use crate::common::{get_arg, get_arg_or};
+use crate::ResultType;
+use anyhow::Context as AnyhowCtx;
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::SystemTime,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+    time::{interval, Duration},
+};
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    server::TlsStream,
+    TlsAcceptor,
+};
+
+const TLS_HANDSHAKE_RECORD: u8 = 0x16;
+const TLS_HANDSHAKE_TIMEOUT: u64 = 10_000;
+const RELOAD_INTERVAL: u64 = 10; // in seconds
+
+lazy_static::lazy_static! {
+    static ref CONFIG: RwLock<Option<Arc<ServerConfig>>> = Default::default();
+}
+
+static TLS_ONLY: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// A stream accepted on a listener that may terminate TLS.
+pub enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+/// Enables TLS termination if `tls-cert` and `tls-key` are set, and reloads
+/// them whenever either file changes.
+pub async fn init() -> ResultType<()> {
+    let cert = get_arg("tls-cert");
+    let key = get_arg("tls-key");
+    if cert.is_empty() || key.is_empty() {
+        return Ok(());
+    }
+    let tls_only = get_arg_or("tls-only", "N".to_owned()).to_uppercase() == "Y";
+    TLS_ONLY.store(tls_only, std::sync::atomic::Ordering::SeqCst);
+    *CONFIG.write().unwrap() = Some(load_config(&cert, &key)?);
+    log::info!("TLS enabled with {} and {}, tls-only: {}", cert, key, tls_only);
+    tokio::spawn(async move {
+        let mut mtimes = (get_mtime(&cert), get_mtime(&key));
+        let mut timer = interval(Duration::from_secs(RELOAD_INTERVAL));
+        loop {
+            timer.tick().await;
+            let tmp = (get_mtime(&cert), get_mtime(&key));
+            if tmp == mtimes {
+                continue;
+            }
+            mtimes = tmp;
+            match load_config(&cert, &key) {
+                Ok(config) => {
+                    *CONFIG.write().unwrap() = Some(config);
+                    log::info!("TLS certificate reloaded");
+                }
+                Err(err) => log::error!("Failed to reload TLS certificate: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn get_mtime(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn load_config(cert: &str, key: &str) -> ResultType<Arc<ServerConfig>> {
+    let certs: Vec<Certificate> =
+        rustls_pemfile::certs(&mut BufReader::new(File::open(cert).context(cert.to_owned())?))?
+            .into_iter()
+            .map(Certificate)
+            .collect();
+    if certs.is_empty() {
+        anyhow::bail!("No certificate found in {}", cert);
+    }
+    let key = rustls_pemfile::read_all(&mut BufReader::new(
+        File::open(key).context(key.to_owned())?,
+    ))?
+    .into_iter()
+    .find_map(|item| match item {
+        rustls_pemfile::Item::RSAKey(k)
+        | rustls_pemfile::Item::PKCS8Key(k)
+        | rustls_pemfile::Item::ECKey(k) => Some(PrivateKey(k)),
+        _ => None,
+    })
+    .with_context(|| format!("No private key found in {key}"))?;
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(Arc::new(config))
+}
+
+/// The current certificate configuration, `None` if TLS is not enabled.
+pub fn server_config() -> Option<Arc<ServerConfig>> {
+    CONFIG.read().unwrap().clone()
+}
+
+/// Terminates TLS on `stream` if it starts with a TLS handshake. Plain
+/// connections are passed through unless `tls-only` is set, so clients and
+/// relays that do not speak TLS keep working on the same port.
+pub async fn accept(stream: TcpStream) -> ResultType<MaybeTlsStream> {
+    let acceptor = match server_config() {
+        Some(config) => TlsAcceptor::from(config),
+        None => return Ok(MaybeTlsStream::Plain(stream)),
+    };
+    let mut buf = [0u8; 1];
+    crate::common::timeout(TLS_HANDSHAKE_TIMEOUT, stream.peek(&mut buf)).await??;
+    if buf[0] == TLS_HANDSHAKE_RECORD {
+        let stream = crate::common::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await??;
+        Ok(MaybeTlsStream::Tls(Box::new(stream)))
+    } else if TLS_ONLY.load(std::sync::atomic::Ordering::SeqCst) {
+        anyhow::bail!("Plain connection refused in tls-only mode")
+    } else {
+        Ok(MaybeTlsStream::Plain(stream))
+    }
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_flush(cx),
+            Self::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Tls(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
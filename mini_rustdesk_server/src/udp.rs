@@ -9,9 +9,11 @@ use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
 use tokio_socks::{udp::Socks5UdpFramed, IntoTargetAddr, TargetAddr, ToProxyAddrs};
 use tokio_util::{codec::BytesCodec, udp::UdpFramed};
 use crate::common::timeout;
+use crate::proxy::{Proxy, ProxyScheme};
+use crate::metrics::{Metrics, MetricsSnapshot};
 pub enum FramedSocket {
-    Direct(UdpFramed<BytesCodec>),
-    ProxySocks(Socks5UdpFramed),
+    Direct(UdpFramed<BytesCodec>, Metrics),
+    ProxySocks(Socks5UdpFramed, Metrics),
 }
 
 fn new_socket(addr: SocketAddr, reuse: bool, buf_size: usize) -> Result<Socket, std::io::Error> {
@@ -59,13 +61,16 @@ impl FramedSocket {
             .await?
             .next()
             .context("could not resolve to any address")?;
-        Ok(Self::Direct(UdpFramed::new(
-            UdpSocket::from_std(new_socket(addr, reuse, buf_size)?.into_udp_socket())?,
-            BytesCodec::new(),
-        )))
+        Ok(Self::Direct(
+            UdpFramed::new(
+                UdpSocket::from_std(new_socket(addr, reuse, buf_size)?.into_udp_socket())?,
+                BytesCodec::new(),
+            ),
+            Metrics::default(),
+        ))
     }
 
-    pub async fn new_proxy<'a, 't, P: ToProxyAddrs, T: ToSocketAddrs>(
+    pub async fn new_proxy<'a, P: ToProxyAddrs, T: ToSocketAddrs>(
         proxy: P,
         local: T,
         username: &'a str,
@@ -86,7 +91,28 @@ impl FramedSocket {
             framed.local_addr(),
             framed.socks_addr()
         );
-        Ok(Self::ProxySocks(framed))
+        Ok(Self::ProxySocks(framed, Metrics::default()))
+    }
+
+    /// Like `new_proxy`, with the proxy given as a URL. Only socks5 proxies can
+    /// relay udp, HTTP CONNECT tunnels are tcp only.
+    pub async fn new_proxy_url<T: ToSocketAddrs>(
+        proxy_url: &str,
+        local: T,
+        ms_timeout: u64,
+    ) -> ResultType<Self> {
+        let proxy = Proxy::from_url(proxy_url)?;
+        if proxy.scheme != ProxyScheme::Socks5 {
+            anyhow::bail!("Udp is not supported by {:?} proxies", proxy.scheme);
+        }
+        Self::new_proxy(
+            proxy.addr.as_str(),
+            local,
+            &proxy.username,
+            &proxy.password,
+            ms_timeout,
+        )
+        .await
     }
 
     #[inline]
@@ -97,13 +123,18 @@ impl FramedSocket {
     ) -> ResultType<()> {
         let addr = addr.into_target_addr()?.to_owned();
         let send_data = Bytes::from(msg.write_to_bytes()?);
+        let len = send_data.len();
         match self {
-            Self::Direct(f) => {
+            Self::Direct(f, m) => {
                 if let TargetAddr::Ip(addr) = addr {
-                    f.send((send_data, addr)).await?
+                    f.send((send_data, addr)).await?;
+                    m.on_sent(len);
                 }
             }
-            Self::ProxySocks(f) => f.send((send_data, addr)).await?,
+            Self::ProxySocks(f, m) => {
+                f.send((send_data, addr)).await?;
+                m.on_sent(len);
+            }
         };
         Ok(())
     }
@@ -118,12 +149,32 @@ impl FramedSocket {
         let addr = addr.into_target_addr()?.to_owned();
 
         match self {
-            Self::Direct(f) => {
+            Self::Direct(f, m) => {
                 if let TargetAddr::Ip(addr) = addr {
-                    f.send((Bytes::from(msg), addr)).await?
+                    f.send((Bytes::from(msg), addr)).await?;
+                    m.on_sent(msg.len());
                 }
             }
-            Self::ProxySocks(f) => f.send((Bytes::from(msg), addr)).await?,
+            Self::ProxySocks(f, m) => {
+                f.send((Bytes::from(msg), addr)).await?;
+                m.on_sent(msg.len());
+            }
+        };
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn send_bytes(&mut self, bytes: Bytes, addr: SocketAddr) -> ResultType<()> {
+        let len = bytes.len();
+        match self {
+            Self::Direct(f, m) => {
+                f.send((bytes, addr)).await?;
+                m.on_sent(len);
+            }
+            Self::ProxySocks(f, m) => {
+                f.send((bytes, addr.into_target_addr()?.to_owned())).await?;
+                m.on_sent(len);
+            }
         };
         Ok(())
     }
@@ -131,15 +182,19 @@ impl FramedSocket {
     #[inline]
     pub async fn next(&mut self) -> Option<ResultType<(BytesMut, TargetAddr<'static>)>> {
         match self {
-            Self::Direct(f) => match f.next().await {
+            Self::Direct(f, m) => match f.next().await {
                 Some(Ok((data, addr))) => {
+                    m.on_received(data.len());
                     Some(Ok((data, addr.into_target_addr().ok()?.to_owned())))
                 }
                 Some(Err(e)) => Some(Err(anyhow!(e))),
                 None => None,
             },
-            Self::ProxySocks(f) => match f.next().await {
-                Some(Ok((data, _))) => Some(Ok((data.data, data.dst_addr))),
+            Self::ProxySocks(f, m) => match f.next().await {
+                Some(Ok((data, _))) => {
+                    m.on_received(data.data.len());
+                    Some(Ok((data.data, data.dst_addr)))
+                }
                 Some(Err(e)) => Some(Err(anyhow!(e))),
                 None => None,
             },
@@ -151,21 +206,22 @@ impl FramedSocket {
         &mut self,
         ms: u64,
     ) -> Option<ResultType<(BytesMut, TargetAddr<'static>)>> {
-        if let Ok(res) =
-            tokio::time::timeout(std::time::Duration::from_millis(ms), self.next()).await
-        {
-            res
-        } else {
-            None
-        }
+        tokio::time::timeout(std::time::Duration::from_millis(ms), self.next())
+            .await
+            .unwrap_or_default()
     }
 
     pub fn local_addr(&self) -> Option<SocketAddr> {
-        if let FramedSocket::Direct(x) = self {
+        if let FramedSocket::Direct(x, _) = self {
             if let Ok(v) = x.get_ref().local_addr() {
                 return Some(v);
             }
         }
         None
     }
+    pub fn metrics(&self) -> MetricsSnapshot {
+        match self {
+            Self::Direct(_, m) | Self::ProxySocks(_, m) => m.snapshot(),
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use mini_rustdesk_server::{
+    proxy::{Proxy, ProxyScheme},
+    tcp::FramedStream,
+};
+use std::net::IpAddr;
+
+const TIMEOUT: u64 = 5_000; // in ms
+// nat test, rendezvous, relay, rendezvous websocket, relay websocket
+const PORTS: [u16; 5] = [21115, 21116, 21117, 21118, 21119];
+
+async fn check_port(host: &str, port: u16, proxy: Option<&str>) -> Result<(), String> {
+    let target = format!("{host}:{port}");
+    let res = match proxy {
+        None => FramedStream::new(target.as_str(), None, TIMEOUT).await,
+        Some(proxy) => FramedStream::connect_url(proxy, &target, None, TIMEOUT).await,
+    };
+    res.map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn check_dns(server: &str) {
+    if let Ok(ip) = server.parse::<IpAddr>() {
+        match dns_lookup::lookup_addr(&ip) {
+            Ok(name) => println!("Reverse lookup of {ip}: {name}"),
+            Err(e) => println!("Reverse lookup of {ip} failed: {e}"),
+        }
+        return;
+    }
+    match dns_lookup::lookup_host(server) {
+        Ok(ips) => {
+            for ip in ips {
+                let name = dns_lookup::lookup_addr(&ip).unwrap_or_else(|_| "-".to_owned());
+                println!("{server} resolves to {ip} ({name})");
+            }
+        }
+        Err(e) => println!("Lookup of {server} failed: {e}"),
+    }
+}
+
+/// Checks name resolution of `server` and whether its ports can be reached,
+/// optionally through an `http://` or `socks5://` proxy.
+pub fn doctor(server: &str, proxy: Option<&str>) -> Result<(), String> {
+    let parsed = proxy.map(Proxy::from_url).transpose().map_err(|e| e.to_string())?;
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    println!("Checking server: {server}");
+    if let Some(parsed) = parsed.as_ref() {
+        let kind = match parsed.scheme {
+            ProxyScheme::Http => "HTTP",
+            ProxyScheme::Socks5 => "Socks5",
+        };
+        println!("Using {kind} proxy {}", parsed.addr);
+    } else {
+        check_dns(server);
+    }
+    for port in PORTS {
+        match rt.block_on(check_port(server, port, proxy)) {
+            Ok(()) => println!("Port {port}: OK"),
+            Err(e) => println!("Port {port}: FAILED, {e}"),
+        }
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+mod doctor;
 use sodiumoxide::crypto::sign;
 use std::{
     env,
@@ -12,7 +13,13 @@ fn print_help() {
 Available Commands:
     genkeypair                                   Generate a new keypair
     validatekeypair [public key] [secret key]    Validate an existing keypair
-    doctor [rustdesk-server]                     Check for server connection problems"
+    doctor [rustdesk-server] [proxy]             Check for server connection problems,
+                                                 proxy as http://[user:pass@]host:port
+                                                 or socks5://[user:pass@]host:port
+    ipc [socket] status                          Show the state of a running server
+    ipc [socket] kick [uuid]                     Close a relay session
+    ipc [socket] config [name] [value]           Change a setting of a running server,
+                                                 name as the argument or its env var"
     );
     process::exit(0x0001);
 }
@@ -44,6 +51,36 @@ fn validate_keypair(pk: &str, sk: &str) -> Result<(), &'static str> {
     Ok(())
 }
 
+#[cfg(unix)]
+fn ipc(path: &str, args: &[String]) -> Result<(), String> {
+    use mini_rustdesk_server::ipc::{request, Command};
+    let cmd = match (args.first().map(|x| x.to_lowercase()).as_deref(), args.len()) {
+        (Some("status"), 1) => Command::Status,
+        (Some("kick"), 2) => Command::Kick {
+            uuid: args[1].clone(),
+        },
+        (Some("config"), 3) => Command::Config {
+            name: args[1].clone(),
+            value: args[2].clone(),
+        },
+        _ => return Err("Usage: ipc [socket] status | kick [uuid] | config [name] [value]".to_owned()),
+    };
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let res = rt
+        .block_on(request(path, &cmd))
+        .map_err(|e| format!("Failed to reach {path}: {e}"))?;
+    if !res.ok {
+        return Err(res.error);
+    }
+    if !res.data.is_null() {
+        println!("{:#}", res.data);
+    }
+    Ok(())
+}
+
 fn error_then_help(msg: &str) {
     println!("ERROR: {msg}\n");
     print_help();
@@ -69,6 +106,26 @@ fn main() {
             }
             println!("Key pair is VALID");
         }
+        "doctor" => {
+            if args.len() <= 2 {
+                error_then_help("You must supply the rustdesk-server address");
+            }
+            let proxy = args.get(3).map(|x| x.as_str());
+            if let Err(e) = doctor::doctor(args[2].as_str(), proxy) {
+                println!("{e}");
+                process::exit(0x0001);
+            }
+        }
+        #[cfg(unix)]
+        "ipc" => {
+            if args.len() <= 3 {
+                error_then_help("You must supply the ipc socket and a command");
+            }
+            if let Err(e) = ipc(args[2].as_str(), &args[3..]) {
+                println!("{e}");
+                process::exit(0x0001);
+            }
+        }
         _ => print_help(),
     }
 }